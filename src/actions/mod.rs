@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use std::{any::Any, collections::VecDeque};
+use std::{any::Any, collections::VecDeque, fmt};
 
 use crate::states::{GameState, TurnSet};
 
@@ -19,6 +19,7 @@ impl Plugin for ActionsPlugin {
             .add_event::<InvalidPlayerActionEvent>()
             .add_event::<GameOverEvent>()
             .add_event::<NextLevelEvent>()
+            .add_event::<PreviousLevelEvent>()
             .configure_sets(
                 Update,
                 (ActionSet::Planning, ActionSet::Late).in_set(TurnSet::Logic),
@@ -35,10 +36,7 @@ impl Plugin for ActionsPlugin {
                     .run_if(on_event::<TickEvent>())
                     .in_set(ActionSet::Late),
             )
-            .add_systems(
-                OnExit(GameState::PlayerInput),
-                systems::populate_actor_queue,
-            )
+            .add_systems(OnExit(GameState::PlayerInput), systems::notice_secret_doors)
             .add_systems(
                 Update,
                 (
@@ -51,14 +49,65 @@ impl Plugin for ActionsPlugin {
     }
 }
 
+/// energy spent by a normal action, also what an actor needs to be allowed to act
+pub const ACTION_COST: u32 = 100;
+
+/// why an action could not be executed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionError {
+    Blocked(Obstacle),
+    OutOfRange,
+    NoTarget,
+    // the entity lacks a component or resource the action needs, usually because it was despawned
+    MissingComponent(&'static str),
+    // something the actor would have to spend or carry, like a key
+    InsufficientResource(&'static str),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Obstacle {
+    Wall,
+    Door,
+    Piece,
+}
+
+impl ActionError {
+    /// error for a missing component or resource, named after its type
+    pub fn missing<T>() -> Self {
+        let name = std::any::type_name::<T>();
+        ActionError::MissingComponent(name.rsplit("::").next().unwrap_or(name))
+    }
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionError::Blocked(Obstacle::Wall) => write!(f, "A wall blocks your way"),
+            ActionError::Blocked(Obstacle::Door) => write!(f, "A door blocks your way"),
+            ActionError::Blocked(Obstacle::Piece) => write!(f, "Something is in the way"),
+            ActionError::OutOfRange => write!(f, "That is too far away"),
+            ActionError::NoTarget => write!(f, "There is nothing there"),
+            ActionError::MissingComponent(name) => write!(f, "Missing {}", name),
+            ActionError::InsufficientResource(name) => write!(f, "You need a {}", name),
+        }
+    }
+}
+
 pub trait Action: Send + Sync {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()>;
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError>;
     fn as_any(&self) -> &dyn Any;
+    /// energy the acting entity spends, only counts for the action picked from its `Actor`
+    fn cost(&self) -> u32 {
+        ACTION_COST
+    }
 }
 
 #[derive(Default, Resource)]
 pub struct PendingActions(pub Vec<Box<dyn Action>>);
 
+/// actors that get to act before time passes again, the one in front is planned for and acts next
+///
+/// player input queues the player, the scheduler refills it with npcs whenever it runs empty
 #[derive(Default, Resource)]
 pub struct ActorQueue(pub VecDeque<Entity>);
 
@@ -68,8 +117,9 @@ pub struct TickEvent;
 pub struct NextActorEvent;
 #[derive(Event)]
 pub struct ActionsCompleteEvent;
+/// carries the reason the first of the player's actions failed
 #[derive(Event)]
-pub struct InvalidPlayerActionEvent;
+pub struct InvalidPlayerActionEvent(pub ActionError);
 #[derive(Event)]
 pub struct ActionExecutedEvent(pub Box<dyn Action>);
 
@@ -79,6 +129,9 @@ pub struct GameOverEvent;
 #[derive(Event)]
 pub struct NextLevelEvent;
 
+#[derive(Event)]
+pub struct PreviousLevelEvent;
+
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 enum ActionSet {
     Planning,
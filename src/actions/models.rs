@@ -1,27 +1,39 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 
 use bevy::prelude::*;
+use rand::seq::IteratorRandom;
 
-use crate::board::components::Wall;
-use crate::board::{components::Position, CurrentBoard};
-use crate::pieces::components::{Health, ItemContainer, ItemPicker, Occupier, Portal};
-use crate::player::Player;
-use crate::vectors::{cast_line, Vector2Int};
+use crate::board::components::{Door, SecretDoor, Tile, TileEffect, TileKind, VisionBlocker, Wall};
+use crate::board::{components::Position, CurrentBoard, GameRng, ValidSpots};
+use crate::messages::{entity_name, log, Severity};
+use crate::pieces::components::{
+    Alerted, Health, Hidden, ItemContainer, ItemPicker, Occupier, Stairs, Trap, TrapKind, Walk,
+};
+use crate::pieces::equipment::Equipment;
+use crate::pieces::spawn_projectile;
+use crate::player::{inventory::Inventory, Player};
+use crate::vectors::{cast_line, Grid, Vector2Int};
 
-use super::{Action, GameOverEvent, NextLevelEvent};
+use super::{
+    Action, ActionError, GameOverEvent, NextLevelEvent, Obstacle, PreviousLevelEvent, ACTION_COST,
+};
 
 pub struct DamageAction(pub Entity, pub u32);
 impl Action for DamageAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
         let Some(mut health) = world.get_mut::<Health>(self.0) else {
-            return Err(());
+            return Err(ActionError::missing::<Health>());
         };
         health.current.min = health.current.min.saturating_sub(self.1);
         if health.current.min == 0 {
             // the unit is killed
             // if its a player send the gameover event
             if world.get::<Player>(self.0).is_some() {
+                log(world, Severity::Danger, "you die");
                 world.send_event(GameOverEvent);
+            } else {
+                let name = entity_name(world, self.0);
+                log(world, Severity::Combat, format!("{} dies", name));
             }
             despawn_children(world, self.0);
             world.despawn(self.0);
@@ -58,54 +70,116 @@ pub struct MeleeHitAction {
     pub attacker: Entity,
     pub target: Vector2Int,
     pub damage: u32,
+    // energy the attacker spends on the hit
+    pub cost: u32,
 }
 impl Action for MeleeHitAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
-        let attacker_position = world.get::<Position>(self.attacker).ok_or(())?;
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let attacker_position = world
+            .get::<Position>(self.attacker)
+            .ok_or(ActionError::missing::<Position>())?;
         if attacker_position.v.manhattan(self.target) > 1 {
-            return Err(());
+            return Err(ActionError::OutOfRange);
         };
         let target_entities = world
             .query_filtered::<(Entity, &Position), With<Health>>()
             .iter(world)
             .filter(|(_, p)| p.v == self.target)
+            .map(|(e, _)| e)
             .collect::<Vec<_>>();
         if target_entities.is_empty() {
-            return Err(());
+            return Err(ActionError::NoTarget);
         };
+        let attacker = entity_name(world, self.attacker);
+        for target in target_entities.iter() {
+            let (severity, text) = match (
+                world.get::<Player>(self.attacker).is_some(),
+                world.get::<Player>(*target).is_some(),
+            ) {
+                (true, _) => (
+                    Severity::Combat,
+                    format!(
+                        "you hit {} for {}",
+                        entity_name(world, *target),
+                        self.damage
+                    ),
+                ),
+                (false, true) => (
+                    Severity::Danger,
+                    format!("{} hits you for {}", attacker, self.damage),
+                ),
+                (false, false) => (
+                    Severity::Combat,
+                    format!(
+                        "{} hits {} for {}",
+                        attacker,
+                        entity_name(world, *target),
+                        self.damage
+                    ),
+                ),
+            };
+            log(world, severity, text);
+        }
         let result = target_entities
             .iter()
-            .map(|e| Box::new(DamageAction(e.0, self.damage)) as Box<dyn Action>)
+            .map(|e| Box::new(DamageAction(*e, self.damage)) as Box<dyn Action>)
             .collect::<Vec<_>>();
         Ok(result)
     }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+    fn cost(&self) -> u32 {
+        self.cost
+    }
 }
 
 pub struct WalkAction(pub Entity, pub Vector2Int);
 impl Action for WalkAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
-        let board = world.get_resource::<CurrentBoard>().ok_or(())?;
-        if !board.tiles.contains_key(&self.1) {
-            return Err(());
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let board = world
+            .get_resource::<CurrentBoard>()
+            .ok_or(ActionError::missing::<CurrentBoard>())?;
+        // walking off the board runs into the wall around it
+        let tile_entity = board
+            .get_tile(self.1)
+            .ok_or(ActionError::Blocked(Obstacle::Wall))?;
+        let kind = world
+            .get::<Tile>(tile_entity)
+            .ok_or(ActionError::missing::<Tile>())?
+            .kind;
+        if !kind.is_walkable() {
+            return Err(ActionError::Blocked(Obstacle::Wall));
         };
 
-        if world
-            .query_filtered::<&Position, With<Occupier>>()
+        if let Some(is_door) = world
+            .query_filtered::<(&Position, Has<Door>), With<Occupier>>()
             .iter(world)
-            .any(|p| p.v == self.1)
+            .find(|(p, _)| p.v == self.1)
+            .map(|(_, is_door)| is_door)
         {
-            return Err(());
+            return Err(ActionError::Blocked(match is_door {
+                true => Obstacle::Door,
+                false => Obstacle::Piece,
+            }));
         };
 
-        let mut position = world.get_mut::<Position>(self.0).ok_or(())?;
+        let mut position = world
+            .get_mut::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?;
         position.v = self.1;
 
         let mut actions: Vec<Box<dyn Action>> = vec![Box::new(PickupAction(self.0, position.v))];
         if world.get::<Player>(self.0).is_some() {
-            actions.push(Box::new(NextLevelAction(self.0, self.1)));
+            actions.push(Box::new(UseStairsAction(self.0, self.1)));
+        }
+        match kind.on_enter() {
+            Some(TileEffect::Damage(value)) => actions.push(Box::new(DamageAction(self.0, value))),
+            Some(TileEffect::Fall) => actions.push(Box::new(FallAction(self.0))),
+            None => (),
+        }
+        if let Some(trap) = find_trap(world, self.1) {
+            actions.push(Box::new(TriggerTrapAction(self.0, trap)));
         }
         Ok(actions)
     }
@@ -114,26 +188,293 @@ impl Action for WalkAction {
     }
 }
 
+/// finds the door standing at `v`
+fn find_door(world: &mut World, v: Vector2Int) -> Option<Entity> {
+    world
+        .query_filtered::<(Entity, &Position), With<Door>>()
+        .iter(world)
+        .find(|(_, p)| p.v == v)
+        .map(|(e, _)| e)
+}
+
+/// finds the undiscovered secret door standing at `v`
+fn find_secret_door(world: &mut World, v: Vector2Int) -> Option<Entity> {
+    world
+        .query_filtered::<(Entity, &Position), With<SecretDoor>>()
+        .iter(world)
+        .find(|(_, p)| p.v == v)
+        .map(|(e, _)| e)
+}
+
+pub struct OpenDoorAction(pub Entity, pub Vector2Int);
+impl Action for OpenDoorAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let position = world
+            .get::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?;
+        if position.v.manhattan(self.1) != 1 {
+            return Err(ActionError::OutOfRange);
+        }
+        if let Some(secret) = find_secret_door(world, self.1) {
+            // npcs know the secret doors of their level, using one gives it away
+            if world.get::<Player>(self.0).is_some() {
+                return Err(ActionError::NoTarget);
+            }
+            return Ok(vec![
+                Box::new(RevealSecretDoorAction(secret)),
+                Box::new(OpenDoorAction(self.0, self.1)),
+            ]);
+        }
+        let door_entity = find_door(world, self.1).ok_or(ActionError::NoTarget)?;
+        let door = world
+            .get::<Door>(door_entity)
+            .ok_or(ActionError::missing::<Door>())?;
+        if door.open {
+            return Err(ActionError::NoTarget);
+        }
+
+        if door.locked {
+            // unlocking spends a key from the inventory
+            let mut inventory = world
+                .get_mut::<Inventory>(self.0)
+                .ok_or(ActionError::missing::<Inventory>())?;
+            let key = inventory
+                .items
+                .iter()
+                .position(|i| i.is_key())
+                .ok_or(ActionError::InsufficientResource("key"))?;
+            inventory.items.remove(key);
+            if let Some(parent) = world.get::<Parent>(door_entity).map(|p| p.get()) {
+                if let Some(mut tile) = world.get_mut::<Tile>(parent) {
+                    tile.kind = TileKind::Door { locked: false };
+                }
+            }
+        }
+
+        let mut door = world
+            .get_mut::<Door>(door_entity)
+            .ok_or(ActionError::missing::<Door>())?;
+        door.open = true;
+        door.locked = false;
+        world
+            .entity_mut(door_entity)
+            .remove::<(Occupier, VisionBlocker)>();
+        Ok(Vec::new())
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+pub struct CloseDoorAction(pub Entity, pub Vector2Int);
+impl Action for CloseDoorAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let position = world
+            .get::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?;
+        if position.v.manhattan(self.1) != 1 {
+            return Err(ActionError::OutOfRange);
+        }
+        let door_entity = find_door(world, self.1).ok_or(ActionError::NoTarget)?;
+        if !world
+            .get::<Door>(door_entity)
+            .ok_or(ActionError::missing::<Door>())?
+            .open
+        {
+            return Err(ActionError::NoTarget);
+        }
+        // something is standing in the doorway
+        if world
+            .query_filtered::<&Position, With<Occupier>>()
+            .iter(world)
+            .any(|p| p.v == self.1)
+        {
+            return Err(ActionError::Blocked(Obstacle::Piece));
+        }
+
+        world
+            .get_mut::<Door>(door_entity)
+            .ok_or(ActionError::missing::<Door>())?
+            .open = false;
+        world
+            .entity_mut(door_entity)
+            .insert((Occupier, VisionBlocker));
+        Ok(Vec::new())
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// turns a secret door into a plain closed door
+pub struct RevealSecretDoorAction(pub Entity);
+impl Action for RevealSecretDoorAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        world
+            .get::<SecretDoor>(self.0)
+            .ok_or(ActionError::missing::<SecretDoor>())?;
+        let v = world
+            .get::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?
+            .v;
+        let tile_entity = world
+            .get::<Parent>(self.0)
+            .ok_or(ActionError::missing::<Parent>())?
+            .get();
+        world
+            .get_mut::<Tile>(tile_entity)
+            .ok_or(ActionError::missing::<Tile>())?
+            .kind = TileKind::Door { locked: false };
+        world.entity_mut(self.0).despawn_recursive();
+
+        let door = world
+            .spawn((
+                Door {
+                    open: false,
+                    locked: false,
+                },
+                Position { v },
+                Occupier,
+                VisionBlocker,
+            ))
+            .id();
+        world.entity_mut(tile_entity).add_child(door);
+        Ok(Vec::new())
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// the entity drops into a chasm, the player lands on the next level
+pub struct FallAction(pub Entity);
+impl Action for FallAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        if world.get::<Player>(self.0).is_some() {
+            world.send_event(NextLevelEvent);
+        } else {
+            world.get_entity(self.0).ok_or(ActionError::NoTarget)?;
+            despawn_recursive(world, self.0);
+        }
+        Ok(Vec::new())
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// digs at the wall next to the entity, the wall turns into floor once it is worn down
+///
+/// needs a digging tool in the weapon slot, every use takes a turn
 pub struct DigAction(pub Entity, pub Vector2Int);
 impl Action for DigAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let position = world
+            .get::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?;
+        if position.v.manhattan(self.1) != 1 {
+            return Err(ActionError::OutOfRange);
+        }
+        let power = world
+            .get::<Equipment>(self.0)
+            .and_then(|e| e.weapon.as_ref())
+            .and_then(|item| item.as_equippable())
+            .and_then(|tool| tool.dig_power())
+            .ok_or(ActionError::InsufficientResource("digging tool"))?;
+
+        let board = world
+            .get_resource::<CurrentBoard>()
+            .ok_or(ActionError::missing::<CurrentBoard>())?;
+        let tile_entity = board.get_tile(self.1).ok_or(ActionError::NoTarget)?;
         let wall_entity = world
-            .query_filtered::<(Entity, &Position, &Occupier), With<Wall>>()
-            .iter(world)
-            .find_map(|(entity, position, _)| {
-                if position.v == self.1 {
-                    Some(entity)
-                } else {
-                    None
-                }
-            });
-        if let Some(wall_entity) = wall_entity {
-            despawn_children(world, wall_entity);
-            world.despawn(wall_entity);
-        } else {
-            return Err(());
+            .get::<Children>(tile_entity)
+            .ok_or(ActionError::missing::<Children>())?
+            .iter()
+            .copied()
+            .find(|child| world.get::<Wall>(*child).is_some())
+            .ok_or(ActionError::NoTarget)?;
+
+        let mut wall = world
+            .get_mut::<Wall>(wall_entity)
+            .ok_or(ActionError::missing::<Wall>())?;
+        // walls without durability can't be dug through
+        let durability = wall
+            .durability
+            .ok_or(ActionError::Blocked(Obstacle::Wall))?
+            .saturating_sub(power);
+        wall.durability = Some(durability);
+        if durability > 0 {
+            return Ok(Vec::new());
         }
 
+        // the wall's tile turns into floor, the tile entity itself stays on the board
+        world
+            .get_mut::<Tile>(tile_entity)
+            .ok_or(ActionError::missing::<Tile>())?
+            .kind = TileKind::Floor;
+        world.entity_mut(wall_entity).despawn_recursive();
+        Ok(Vec::new())
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// the tiles a shot from `source` passes on its way to `target`
+///
+/// leaves out the shooter's own tile, stops after `range` tiles or on the first blocker
+pub fn shot_path(
+    source: Vector2Int,
+    target: Vector2Int,
+    range: u32,
+    mut blockers: Grid<bool>,
+) -> Vec<Vector2Int> {
+    blockers.set(source, false);
+    cast_line(source, target, &blockers)
+        .into_iter()
+        .skip(1)
+        .take(range as usize)
+        .collect()
+}
+
+/// what stops a shot, pieces in the way and tiles that can't be seen through
+///
+/// `targeting::update_shot_preview` builds the same grid from queries
+fn shot_blockers(world: &mut World) -> Option<Grid<bool>> {
+    let (min, max) = world.get_resource::<CurrentBoard>()?.tiles.bounds();
+    let mut blockers = Grid::new(min, max, false);
+    for position in world
+        .query_filtered::<&Position, With<Occupier>>()
+        .iter(world)
+    {
+        blockers.set(position.v, true);
+    }
+    for (tile, position) in world.query::<(&Tile, &Position)>().iter(world) {
+        if tile.kind.blocks_vision() {
+            blockers.set(position.v, true);
+        }
+    }
+    Some(blockers)
+}
+
+/// shoots the entity's ranged weapon at `target`, the shot flies on its own turns
+pub struct FireAction(pub Entity, pub Vector2Int);
+impl Action for FireAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let v = world
+            .get::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?
+            .v;
+        if v == self.1 {
+            return Err(ActionError::NoTarget);
+        }
+        let (range, damage) = world
+            .get::<Equipment>(self.0)
+            .and_then(|e| e.weapon.as_ref())
+            .and_then(|item| item.as_equippable())
+            .and_then(|weapon| Some((weapon.range()?, weapon.damage()?)))
+            .ok_or(ActionError::InsufficientResource("ranged weapon"))?;
+        spawn_projectile(world, v, self.1, damage, range);
         Ok(Vec::new())
     }
     fn as_any(&self) -> &dyn std::any::Any {
@@ -145,24 +486,26 @@ pub struct ProjectileShootAction {
     pub entity: Entity,
     pub target: Vector2Int,
     pub damage: u32,
+    pub range: u32,
 }
 impl Action for ProjectileShootAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
-        let blockers: HashSet<Vector2Int> = world
-            .query_filtered::<&Position, With<Occupier>>()
-            .iter(world)
-            .map(|p| p.v)
-            .collect();
-        let current_position = world.get_mut::<Position>(self.entity).ok_or(())?;
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let blockers = shot_blockers(world).ok_or(ActionError::missing::<CurrentBoard>())?;
+        let current_position = world
+            .get::<Position>(self.entity)
+            .ok_or(ActionError::missing::<Position>())?;
 
-        let path = VecDeque::from(cast_line(current_position.v, self.target, &blockers));
+        let path = VecDeque::from(shot_path(
+            current_position.v,
+            self.target,
+            self.range,
+            blockers,
+        ));
 
         if path.is_empty() {
-            println!("No path found");
-            return Err(());
+            return Err(ActionError::NoTarget);
         }
 
-        println!("Shooting along path: {:?}", path);
         Ok(vec![Box::new(ProjectileFlyAction {
             entity: self.entity,
             path,
@@ -180,7 +523,7 @@ pub struct ProjectileFlyAction {
     pub damage: u32,
 }
 impl Action for ProjectileFlyAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
         if let Some(pos) = self.path.front() {
             let collided_entities = world
                 .query_filtered::<(Entity, &Position), With<Health>>()
@@ -193,19 +536,26 @@ impl Action for ProjectileFlyAction {
                     }
                 });
             if let Some(collided_entity) = collided_entities {
-                println!(
-                    "Collided with: {:?} for {} damage",
-                    collided_entity, self.damage
+                let severity = match world.get::<Player>(collided_entity) {
+                    Some(_) => Severity::Danger,
+                    None => Severity::Combat,
+                };
+                let text = format!(
+                    "the projectile hits {} for {}",
+                    entity_name(world, collided_entity),
+                    self.damage
                 );
+                log(world, severity, text);
                 world.despawn(self.entity);
                 return Ok(vec![Box::new(DamageAction(collided_entity, self.damage))]);
             }
         }
 
-        let mut current_position = world.get_mut::<Position>(self.entity).ok_or(())?;
+        let mut current_position = world
+            .get_mut::<Position>(self.entity)
+            .ok_or(ActionError::missing::<Position>())?;
 
         if self.path.is_empty() {
-            println!("Reached destination");
             world.despawn(self.entity);
             return Ok(Vec::new());
         }
@@ -228,9 +578,11 @@ impl Action for ProjectileFlyAction {
 
 pub struct PickupAction(pub Entity, pub Vector2Int);
 impl Action for PickupAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
         // ensure that the entity is an item picker before any expensive work is done
-        world.get::<ItemPicker>(self.0).ok_or(())?;
+        world
+            .get::<ItemPicker>(self.0)
+            .ok_or(ActionError::missing::<ItemPicker>())?;
 
         let target_item_entity = world
             .query_filtered::<(Entity, &ItemContainer, &Position), (With<ItemContainer>, Without<Player>)>()
@@ -239,12 +591,22 @@ impl Action for PickupAction {
             .map(|(e, item_container, _)| (e, item_container));
 
         if target_item_entity.is_none() {
-            return Err(());
+            return Err(ActionError::NoTarget);
         }
 
         // unwrap is safe here
         let target_item = target_item_entity.unwrap().1.item.clone();
-        let _ = target_item.pick_up(world, self.0, target_item_entity.unwrap().0);
+        if target_item
+            .pick_up(world, self.0, target_item_entity.unwrap().0)
+            .is_ok()
+            && world.get::<Player>(self.0).is_some()
+        {
+            log(
+                world,
+                Severity::Info,
+                format!("you pick up the {}", target_item.name().to_lowercase()),
+            );
+        }
         Ok(Vec::new())
     }
     fn as_any(&self) -> &dyn std::any::Any {
@@ -252,29 +614,272 @@ impl Action for PickupAction {
     }
 }
 
-pub struct NextLevelAction(pub Entity, pub Vector2Int);
-impl Action for NextLevelAction {
-    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ()> {
+pub struct UseStairsAction(pub Entity, pub Vector2Int);
+impl Action for UseStairsAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
         // ensure that the entity is a player before any expensive work is done
         if world.get::<Player>(self.0).is_none() {
-            return Err(());
+            return Err(ActionError::missing::<Player>());
         }
 
-        let target_portal = world
-            .query_filtered::<(Entity, &Position), With<Portal>>()
+        let stairs = world
+            .query::<(&Stairs, &Position)>()
+            .iter(world)
+            .find(|(_, p)| p.v == self.1)
+            .map(|(s, _)| *s)
+            .ok_or(ActionError::NoTarget)?;
+
+        match stairs {
+            Stairs::Down => {
+                world.send_event(NextLevelEvent);
+            }
+            Stairs::Up => {
+                world.send_event(PreviousLevelEvent);
+            }
+        }
+        Ok(Vec::new())
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+const SPIKE_DAMAGE: u32 = 3;
+const GAS_DAMAGE: u32 = 2;
+const ALARM_RANGE: i32 = 15;
+const SEARCH_RANGE: i32 = 2;
+
+/// finds the trap at `v`, hidden or not
+fn find_trap(world: &mut World, v: Vector2Int) -> Option<Entity> {
+    world
+        .query_filtered::<(Entity, &Position), With<Trap>>()
+        .iter(world)
+        .find(|(_, p)| p.v == v)
+        .map(|(e, _)| e)
+}
+
+/// the trap (second entity) goes off under the actor, revealing itself
+pub struct TriggerTrapAction(pub Entity, pub Entity);
+impl Action for TriggerTrapAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let kind = world
+            .get::<Trap>(self.1)
+            .ok_or(ActionError::missing::<Trap>())?
+            .kind;
+        let v = world
+            .get::<Position>(self.1)
+            .ok_or(ActionError::missing::<Position>())?
+            .v;
+        let actions: Vec<Box<dyn Action>> = match kind {
+            TrapKind::Spike => vec![Box::new(DamageAction(self.0, SPIKE_DAMAGE))],
+            TrapKind::Teleport => {
+                let target = random_free_spot(world).ok_or(ActionError::NoTarget)?;
+                vec![Box::new(TeleportAction(self.0, target))]
+            }
+            TrapKind::Alarm => vec![Box::new(AlarmAction(v))],
+            TrapKind::PoisonGas => world
+                .query_filtered::<(Entity, &Position), With<Health>>()
+                .iter(world)
+                .filter(|(_, p)| p.v.distance(v) <= 1)
+                .map(|(e, _)| Box::new(DamageAction(e, GAS_DAMAGE)) as Box<dyn Action>)
+                .collect(),
+        };
+        world.entity_mut(self.1).remove::<Hidden>();
+        Ok(actions)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// moves the entity straight to `v`, without passing the tiles in between
+pub struct TeleportAction(pub Entity, pub Vector2Int);
+impl Action for TeleportAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        if world
+            .query_filtered::<&Position, With<Occupier>>()
             .iter(world)
-            .filter(|(_, p)| p.v == self.1)
+            .any(|p| p.v == self.1)
+        {
+            return Err(ActionError::Blocked(Obstacle::Piece));
+        }
+        world
+            .get_mut::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?
+            .v = self.1;
+        Ok(vec![Box::new(PickupAction(self.0, self.1))])
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+/// a random valid spot nothing stands on
+fn random_free_spot(world: &mut World) -> Option<Vector2Int> {
+    let occupied = world
+        .query_filtered::<&Position, With<Occupier>>()
+        .iter(world)
+        .map(|p| p.v)
+        .collect::<Vec<_>>();
+    let spots = world.get_resource::<ValidSpots>()?.0.clone();
+    // the game rng keeps seeded runs reproducible
+    let mut rng = world.get_resource_mut::<GameRng>()?;
+    spots
+        .into_iter()
+        .filter(|v| !occupied.contains(v))
+        .choose(&mut rng.0)
+}
+
+/// alerts every npc in earshot of `v`
+pub struct AlarmAction(pub Vector2Int);
+impl Action for AlarmAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let npcs = world
+            .query_filtered::<(Entity, &Position), With<Walk>>()
+            .iter(world)
+            .filter(|(_, p)| p.v.distance(self.0) <= ALARM_RANGE)
             .map(|(e, _)| e)
             .collect::<Vec<_>>();
+        for entity in npcs {
+            world.entity_mut(entity).insert(Alerted);
+        }
+        Ok(Vec::new())
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
 
-        if target_portal.is_empty() {
-            return Err(());
+/// reveals the hidden traps and secret doors around the entity,
+/// takes a turn even if nothing is found
+pub struct SearchAction(pub Entity);
+impl Action for SearchAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let v = world
+            .get::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?
+            .v;
+        let found = world
+            .query_filtered::<(Entity, &Position), (With<Trap>, With<Hidden>)>()
+            .iter(world)
+            .filter(|(_, p)| p.v.distance(v) <= SEARCH_RANGE)
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>();
+        for entity in found {
+            world.entity_mut(entity).remove::<Hidden>();
         }
+        let secret_doors = world
+            .query_filtered::<(Entity, &Position), With<SecretDoor>>()
+            .iter(world)
+            .filter(|(_, p)| p.v.distance(v) <= SEARCH_RANGE)
+            .map(|(e, _)| Box::new(RevealSecretDoorAction(e)) as Box<dyn Action>)
+            .collect();
+        Ok(secret_doors)
+    }
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
 
-        world.send_event(NextLevelEvent);
+/// removes a trap the entity knows about, from next to it or on top of it
+pub struct DisarmTrapAction(pub Entity, pub Vector2Int);
+impl Action for DisarmTrapAction {
+    fn execute(&mut self, world: &mut World) -> Result<Vec<Box<dyn Action>>, ActionError> {
+        let position = world
+            .get::<Position>(self.0)
+            .ok_or(ActionError::missing::<Position>())?;
+        if position.v.manhattan(self.1) > 1 {
+            return Err(ActionError::OutOfRange);
+        }
+        let trap = find_trap(world, self.1).ok_or(ActionError::NoTarget)?;
+        // a trap nobody has found yet can't be disarmed
+        if world.get::<Hidden>(trap).is_some() {
+            return Err(ActionError::NoTarget);
+        }
+        despawn_recursive(world, trap);
         Ok(Vec::new())
     }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
+    // careful work, takes two turns
+    fn cost(&self) -> u32 {
+        2 * ACTION_COST
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::components::WallMaterial;
+
+    // a row of floor with a wall at x = 2, the walker stands at the origin
+    fn world_with_walker() -> (World, Entity) {
+        let mut world = World::new();
+        let mut tiles = Grid::new(Vector2Int::new(0, 0), Vector2Int::new(2, 0), None);
+        for x in 0..=2 {
+            let kind = match x {
+                2 => TileKind::Wall {
+                    material: WallMaterial::Rock,
+                },
+                _ => TileKind::Floor,
+            };
+            let tile = world
+                .spawn(Tile {
+                    visible: false,
+                    seen: false,
+                    kind,
+                })
+                .id();
+            tiles.set(Vector2Int::new(x, 0), Some(tile));
+        }
+        world.insert_resource(CurrentBoard { tiles });
+        let walker = world
+            .spawn(Position {
+                v: Vector2Int::new(0, 0),
+            })
+            .id();
+        (world, walker)
+    }
+
+    #[test]
+    fn walking_into_a_wall_is_blocked() {
+        let (mut world, walker) = world_with_walker();
+        let result = WalkAction(walker, Vector2Int::new(2, 0)).execute(&mut world);
+        assert_eq!(result.err(), Some(ActionError::Blocked(Obstacle::Wall)));
+        // walking off the board counts as a wall too
+        let result = WalkAction(walker, Vector2Int::new(0, 1)).execute(&mut world);
+        assert_eq!(result.err(), Some(ActionError::Blocked(Obstacle::Wall)));
+        assert_eq!(
+            world.get::<Position>(walker).unwrap().v,
+            Vector2Int::new(0, 0)
+        );
+    }
+
+    #[test]
+    fn walking_into_a_piece_is_blocked() {
+        let (mut world, walker) = world_with_walker();
+        world.spawn((
+            Position {
+                v: Vector2Int::new(1, 0),
+            },
+            Occupier,
+        ));
+        let result = WalkAction(walker, Vector2Int::new(1, 0)).execute(&mut world);
+        assert_eq!(result.err(), Some(ActionError::Blocked(Obstacle::Piece)));
+    }
+
+    #[test]
+    fn melee_without_a_target_fails() {
+        let (mut world, attacker) = world_with_walker();
+        let mut hit = MeleeHitAction {
+            attacker,
+            target: Vector2Int::new(1, 0),
+            damage: 1,
+            cost: ACTION_COST,
+        };
+        assert_eq!(hit.execute(&mut world).err(), Some(ActionError::NoTarget));
+        hit.target = Vector2Int::new(2, 2);
+        assert_eq!(hit.execute(&mut world).err(), Some(ActionError::OutOfRange));
+    }
 }
@@ -1,32 +1,41 @@
 use bevy::prelude::*;
 use rand::prelude::*;
+use std::collections::HashSet;
 
 use crate::actions::models::ProjectileShootAction;
 use crate::board::systems::VISIBILITY_RANGE;
-use crate::board::CurrentBoard;
-use crate::pieces::components::{Melee, Occupier, Projectile, Walk};
+use crate::board::{
+    components::{Door, SecretDoor, Tile, TileKind},
+    CurrentBoard, GameRng,
+};
+use crate::pieces::components::{Alerted, Energy, Melee, Occupier, Projectile, Speed, Walk};
 use crate::player::Player;
 use crate::vectors::{find_path, ORTHO_DIRECTIONS};
 use crate::{board::components::Position, pieces::components::Actor};
 
-use super::models::{MeleeHitAction, WalkAction};
+use super::models::{MeleeHitAction, OpenDoorAction, RevealSecretDoorAction, WalkAction};
 use super::{
-    ActionExecutedEvent, ActionsCompleteEvent, ActorQueue, InvalidPlayerActionEvent,
-    NextActorEvent, PendingActions,
+    ActionError, ActionExecutedEvent, ActionsCompleteEvent, ActorQueue, InvalidPlayerActionEvent,
+    NextActorEvent, PendingActions, ACTION_COST,
 };
 
 const PLAYER_ATTACK_SCORE: i32 = 100;
 const MOVE_SCORE: i32 = 50;
+// time steps the scheduler lets pass looking for a ready actor, guards against nobody having speed
+const MAX_SCHEDULE_STEPS: usize = 1000;
+// turns the player has to spend next to a secret door to notice it
+const SECRET_DOOR_NOTICE_TURNS: u32 = 3;
 
-fn execute_action(mut action: Box<dyn super::Action>, world: &mut World) -> bool {
-    if let Ok(result) = action.execute(world) {
-        if let Some(mut pending) = world.get_resource_mut::<PendingActions>() {
-            pending.0.extend(result);
-        }
-        world.send_event(ActionExecutedEvent(action));
-        return true;
+fn execute_action(
+    mut action: Box<dyn super::Action>,
+    world: &mut World,
+) -> Result<(), ActionError> {
+    let result = action.execute(world)?;
+    if let Some(mut pending) = world.get_resource_mut::<PendingActions>() {
+        pending.0.extend(result);
     }
-    false
+    world.send_event(ActionExecutedEvent(action));
+    Ok(())
 }
 
 pub fn process_action_queue(world: &mut World) {
@@ -38,7 +47,12 @@ pub fn process_action_queue(world: &mut World) {
         return;
     };
     let Some(entity) = queue.0.pop_front() else {
-        world.send_event(ActionsCompleteEvent);
+        // everyone who could act has, let time pass until the next actor is ready
+        if schedule_actors(world) {
+            world.send_event(NextActorEvent);
+        } else {
+            world.send_event(ActionsCompleteEvent);
+        }
         return;
     };
     let Some(mut actor) = world.get_mut::<Actor>(entity) else {
@@ -50,20 +64,69 @@ pub fn process_action_queue(world: &mut World) {
     // highest score first
     possible_actions.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
-    let mut success = false;
+    let mut cost = None;
+    let mut error = ActionError::NoTarget;
     for action in possible_actions {
-        success = success || execute_action(action.0, world);
-        if success {
-            break;
+        let action_cost = action.0.cost();
+        match execute_action(action.0, world) {
+            Ok(()) => {
+                cost = Some(action_cost);
+                break;
+            }
+            // lower scored actions are fallbacks for the same input, e.g. opening a door after
+            // failing to walk through it, so their reason is kept unless they had nothing to act on
+            Err(ActionError::NoTarget) => (),
+            Err(e) => error = e,
         }
     }
-    if !success && world.get::<Player>(entity).is_some() {
-        world.send_event(InvalidPlayerActionEvent);
+    if cost.is_none() && world.get::<Player>(entity).is_some() {
+        world.send_event(InvalidPlayerActionEvent(error));
         return;
     }
+    // npcs that found nothing to do wait, which takes as long as a normal action
+    if let Some(mut energy) = world.get_mut::<Energy>(entity) {
+        energy.0 -= cost.unwrap_or(ACTION_COST) as i32;
+    }
     world.send_event(NextActorEvent);
 }
 
+/// queues the npcs that have the energy to act, handing out energy by speed until someone does
+///
+/// returns false once it is the player's turn instead, npcs go first when both are ready
+fn schedule_actors(world: &mut World) -> bool {
+    let ready = ACTION_COST as i32;
+    for _ in 0..MAX_SCHEDULE_STEPS {
+        let mut npcs = world
+            .query_filtered::<(Entity, &Energy), (With<Actor>, Without<Player>)>()
+            .iter(world)
+            .filter(|(_, energy)| energy.0 >= ready)
+            .map(|(entity, energy)| (entity, energy.0))
+            .collect::<Vec<_>>();
+        if !npcs.is_empty() {
+            // the most energetic go first, entity order keeps ties reproducible
+            npcs.sort_by_key(|(entity, energy)| (-energy, *entity));
+            if let Some(mut queue) = world.get_resource_mut::<ActorQueue>() {
+                queue.0.extend(npcs.into_iter().map(|(entity, _)| entity));
+            }
+            return true;
+        }
+        let player_ready = world
+            .query_filtered::<&Energy, With<Player>>()
+            .iter(world)
+            .any(|energy| energy.0 >= ready);
+        if player_ready {
+            return false;
+        }
+        for (speed, mut energy) in world
+            .query_filtered::<(&Speed, &mut Energy), With<Actor>>()
+            .iter_mut(world)
+        {
+            energy.0 += speed.0 as i32;
+        }
+    }
+    false
+}
+
 fn process_pending_actions(world: &mut World) -> bool {
     let pending = match world.get_resource_mut::<PendingActions>() {
         Some(mut res) => res.0.drain(..).collect::<Vec<_>>(),
@@ -71,53 +134,98 @@ fn process_pending_actions(world: &mut World) -> bool {
     };
     let mut success = false;
     for action in pending {
-        success = success || execute_action(action, world);
+        // every pending action runs, even after one of them succeeded
+        let ok = execute_action(action, world).is_ok();
+        success |= ok;
     }
     success
 }
 
-pub fn populate_actor_queue(
-    query: Query<Entity, (With<Actor>, Without<Player>)>,
-    mut queue: ResMut<ActorQueue>,
+/// counts the turns the player starts next to a secret door, noticing it after a while
+pub fn notice_secret_doors(
+    player_query: Query<&Position, With<Player>>,
+    mut secret_query: Query<(Entity, &Position, &mut SecretDoor)>,
+    mut pending: ResMut<PendingActions>,
 ) {
-    queue.0.extend(query.iter());
+    let Ok(player_position) = player_query.get_single() else {
+        return;
+    };
+    for (entity, position, mut secret) in secret_query.iter_mut() {
+        if position.v.manhattan(player_position.v) != 1 {
+            continue;
+        }
+        secret.noticed += 1;
+        if secret.noticed >= SECRET_DOOR_NOTICE_TURNS {
+            pending.0.push(Box::new(RevealSecretDoorAction(entity)));
+        }
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn plan_walk(
-    mut query: Query<(&Position, &mut Actor), With<Walk>>,
+    mut query: Query<(&Position, &mut Actor, Option<&Alerted>), With<Walk>>,
     queue: Res<ActorQueue>,
     player_query: Query<&Position, With<Player>>,
     occupier_query: Query<&Position, With<Occupier>>,
+    tile_query: Query<&Tile>,
+    door_query: Query<(&Position, &Door)>,
+    secret_query: Query<&Position, With<SecretDoor>>,
     board: Res<CurrentBoard>,
+    mut rng: ResMut<GameRng>,
 ) {
     let Some(entity) = queue.0.front() else {
         return;
     };
-    let Ok((position, mut actor)) = query.get_mut(*entity) else {
+    let Ok((position, mut actor, alerted)) = query.get_mut(*entity) else {
         return;
     };
     let Ok(player_position) = player_query.get_single() else {
         return;
     };
-    if position.v.distance(player_position.v) > VISIBILITY_RANGE {
+    if position.v.distance(player_position.v) > VISIBILITY_RANGE && alerted.is_none() {
         return;
     }
-    // get all possible move targets
+    // npcs know the secret doors of their level and walk through them like any other door
+    let tile_kind = |entity: &Option<Entity>| {
+        entity
+            .and_then(|e| tile_query.get(e).ok())
+            .map(|tile| tile.kind)
+            .filter(|kind| kind.is_walkable() || *kind == TileKind::SecretDoor)
+    };
+    // get all possible move targets, wandering never leads into a hazard
     let positions = ORTHO_DIRECTIONS
         .iter()
         .map(|d| *d + position.v)
+        .filter(|v| match board.tiles.get(*v).and_then(tile_kind) {
+            Some(kind) => kind.on_enter().is_none(),
+            None => false,
+        })
         .collect::<Vec<_>>();
+    // unlocked doors can be opened on the way, so they don't block the path
+    let doors = door_query
+        .iter()
+        .filter(|(_, door)| !door.locked)
+        .map(|(p, _)| p.v)
+        .chain(secret_query.iter().map(|p| p.v))
+        .collect::<HashSet<_>>();
+    let blockers = occupier_query
+        .iter()
+        .map(|p| p.v)
+        .filter(|v| !doors.contains(v))
+        .collect();
     // find possible path to the player
     let path_to_player = find_path(
         position.v,
         player_position.v,
-        &board.tiles.keys().cloned().collect(),
-        &occupier_query.iter().map(|p| p.v).collect(),
+        &board.tiles,
+        |entity| tile_kind(entity).map(|kind| kind.movement_cost()),
+        &blockers,
     );
-    let mut rng = thread_rng();
+    // the game rng keeps seeded runs and replays reproducible
+    let rng = &mut rng.0;
     let actions = positions
         .iter()
-        .map(|v| {
+        .flat_map(|v| {
             // randomize movement choices
             let mut d = rng.gen_range(-10..0);
             if let Some(path) = &path_to_player {
@@ -126,10 +234,17 @@ pub fn plan_walk(
                     d = 5
                 }
             }
-            (
-                Box::new(WalkAction(*entity, *v)) as Box<dyn super::Action>,
-                MOVE_SCORE + d,
-            )
+            [
+                (
+                    Box::new(WalkAction(*entity, *v)) as Box<dyn super::Action>,
+                    MOVE_SCORE + d,
+                ),
+                // only succeeds when the move is blocked by a closed door
+                (
+                    Box::new(OpenDoorAction(*entity, *v)) as Box<dyn super::Action>,
+                    MOVE_SCORE + d,
+                ),
+            ]
         })
         .collect::<Vec<_>>();
     actor.0.extend(actions);
@@ -153,6 +268,7 @@ pub fn plan_melee(
         attacker: *entity,
         target: player_position.v,
         damage: melee.current_damage.min,
+        cost: melee.attack_cost,
     });
     actor.0.push((
         action,
@@ -160,16 +276,65 @@ pub fn plan_melee(
     ))
 }
 
-pub fn process_projectiles(mut query: Query<(&mut Actor, Entity, &Projectile), Added<Projectile>>) {
+pub fn process_projectiles(
+    mut query: Query<(&mut Actor, Entity, &Projectile), Added<Projectile>>,
+    mut rng: ResMut<GameRng>,
+) {
     for (mut actor, entity, projectile) in query.iter_mut() {
-        println!("Processing projectile {:?}", projectile.destination);
+        // rolled on the game rng, so replays hit just as hard
+        let damage = projectile.damage;
+        let damage = rng.0.gen_range(damage.min.min(damage.max)..=damage.max);
         actor.0.push((
             Box::new(ProjectileShootAction {
                 entity,
                 target: projectile.destination,
-                damage: projectile.damage.max,
+                damage,
+                range: projectile.range,
             }),
             0,
         ));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::models::SearchAction;
+    use super::*;
+    use crate::vectors::Vector2Int;
+
+    fn spawn_secret_door(world: &mut World, v: Vector2Int) {
+        let tile = world
+            .spawn(Tile {
+                visible: false,
+                seen: false,
+                kind: TileKind::SecretDoor,
+            })
+            .id();
+        let door = world
+            .spawn((SecretDoor::default(), Position { v }, Occupier))
+            .id();
+        world.entity_mut(tile).add_child(door);
+    }
+
+    #[test]
+    fn search_reveals_every_secret_door_around() {
+        let mut world = World::new();
+        world.init_resource::<PendingActions>();
+        world.init_resource::<Events<ActionExecutedEvent>>();
+        let player = world
+            .spawn(Position {
+                v: Vector2Int::new(0, 0),
+            })
+            .id();
+        spawn_secret_door(&mut world, Vector2Int::new(1, 0));
+        spawn_secret_door(&mut world, Vector2Int::new(-1, 0));
+
+        assert!(execute_action(Box::new(SearchAction(player)), &mut world).is_ok());
+        assert!(process_pending_actions(&mut world));
+
+        let mut secret_doors = world.query::<&SecretDoor>();
+        assert_eq!(secret_doors.iter(&world).count(), 0);
+        let mut doors = world.query::<&Door>();
+        assert_eq!(doors.iter(&world).count(), 2);
+    }
+}
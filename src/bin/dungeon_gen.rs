@@ -0,0 +1,269 @@
+//! generates dungeon floors without starting the game
+//!
+//! `--seed <u64>` seed of the first floor, defaults to 0
+//!
+//! `--count <n>` number of floors, one per seed counting up from `--seed`
+//!
+//! `--depth <n>` depth the recipe is picked and scaled for
+//!
+//! `--recipe <name>` use the named recipe instead of a weighted pick
+//!
+//! `--recipes <path>` recipe file, defaults to `assets/dungeon.recipes.ron`
+//!
+//! `--json` print every floor as a line of json instead of a text map
+//!
+//! floors that are not fully connected are reported on stderr and make the exit code non-zero
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+
+use roguelike::{
+    board::{
+        dungeon::room::PrefabSpawn,
+        recipe::{DungeonRecipe, DungeonRecipes, RECIPES_PATH},
+        systems::{generate_level, GeneratedLevel},
+    },
+    vectors::Vector2Int,
+};
+
+struct Options {
+    seed: u64,
+    count: u64,
+    depth: u32,
+    recipe: Option<String>,
+    recipes: String,
+    json: bool,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, String> {
+        let mut options = Options {
+            seed: 0,
+            count: 1,
+            depth: 0,
+            recipe: None,
+            recipes: format!("assets/{}", RECIPES_PATH),
+            json: false,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+            match arg.as_str() {
+                "--seed" => options.seed = parse(&value()?)?,
+                "--count" => options.count = parse(&value()?)?,
+                "--depth" => options.depth = parse(&value()?)?,
+                "--recipe" => options.recipe = Some(value()?),
+                "--recipes" => options.recipes = value()?,
+                "--json" => options.json = true,
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number {}", value))
+}
+
+#[derive(Serialize)]
+struct FloorReport {
+    seed: u64,
+    depth: u32,
+    recipe: String,
+    connected: bool,
+    // text map rows, top row first
+    map: Vec<String>,
+    rooms: Vec<RoomReport>,
+    // pairs of room ids joined by a tunnel
+    connections: Vec<(usize, usize)>,
+    spawns: Vec<SpawnReport>,
+}
+
+#[derive(Serialize)]
+struct RoomReport {
+    id: usize,
+    area: usize,
+    role: String,
+    centre: (i32, i32),
+    size: usize,
+}
+
+#[derive(Serialize)]
+struct SpawnReport {
+    kind: &'static str,
+    position: (i32, i32),
+}
+
+fn main() -> ExitCode {
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let recipes = match load_recipes(&options.recipes) {
+        Ok(recipes) => recipes,
+        Err(e) => {
+            eprintln!("Could not load recipes from {}: {}", options.recipes, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let named = match &options.recipe {
+        Some(name) => match recipes.recipes.iter().find(|r| &r.name == name) {
+            Some(recipe) => Some(recipe),
+            None => {
+                eprintln!("No recipe named {}", name);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let default_recipe = DungeonRecipe::default();
+
+    let mut failures = 0;
+    for seed in options.seed..options.seed.saturating_add(options.count) {
+        // same rng use as the game, so the first floor of a seed matches what the player gets
+        let mut rng = StdRng::seed_from_u64(seed);
+        let recipe = named
+            .or_else(|| recipes.choose(options.depth, &mut rng))
+            .unwrap_or(&default_recipe);
+        let level = generate_level(recipe, options.depth, &mut rng);
+        let report = report(seed, options.depth, recipe, &level);
+
+        if !report.connected {
+            failures += 1;
+            eprintln!(
+                "Seed {} ({} recipe) is not fully connected",
+                seed, recipe.name
+            );
+        }
+        if options.json {
+            println!("{}", serde_json::to_string(&report).unwrap());
+        } else {
+            println!(
+                "seed {}, depth {}, {} recipe",
+                seed, options.depth, recipe.name
+            );
+            println!("{}\n", report.map.join("\n"));
+        }
+    }
+
+    if failures > 0 {
+        eprintln!(
+            "{} of {} floors are not fully connected",
+            failures, options.count
+        );
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn load_recipes(path: &str) -> Result<DungeonRecipes, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let recipes = ron::de::from_bytes::<DungeonRecipes>(&bytes).map_err(|e| e.to_string())?;
+    recipes.validate()?;
+    Ok(recipes)
+}
+
+fn report(seed: u64, depth: u32, recipe: &DungeonRecipe, level: &GeneratedLevel) -> FloorReport {
+    let spawns = level
+        .key_spots
+        .iter()
+        .map(|v| ("key", *v))
+        .chain(
+            level
+                .dungeon
+                .get_prefab_spawns()
+                .into_iter()
+                .map(|(v, spawn)| match spawn {
+                    PrefabSpawn::Npc => ("npc", v),
+                    PrefabSpawn::Gold(_) => ("gold", v),
+                    PrefabSpawn::Health(_) => ("health", v),
+                }),
+        )
+        .chain(level.stairs.up.map(|v| ("stairs_up", v)))
+        .chain(level.stairs.down.map(|v| ("stairs_down", v)))
+        .chain(level.valid_spots.first().map(|v| ("player", *v)))
+        .map(|(kind, v)| SpawnReport {
+            kind,
+            position: (v.x, v.y),
+        })
+        .collect::<Vec<_>>();
+
+    let connections = level
+        .rooms
+        .iter()
+        .flat_map(|r| r.neighbours.iter().map(move |n| (r.id, *n)))
+        .filter(|(a, b)| a < b)
+        .collect();
+    let rooms = level
+        .rooms
+        .iter()
+        .map(|r| RoomReport {
+            id: r.id,
+            area: r.area,
+            role: format!("{:?}", r.role),
+            centre: (r.centre.x, r.centre.y),
+            size: r.tiles.len(),
+        })
+        .collect();
+
+    FloorReport {
+        seed,
+        depth,
+        recipe: recipe.name.clone(),
+        connected: level.dungeon.is_connected(),
+        map: text_map(level, &spawns),
+        rooms,
+        connections,
+        spawns,
+    }
+}
+
+/// the floor drawn with the same glyphs as hand-authored text maps
+fn text_map(level: &GeneratedLevel, spawns: &[SpawnReport]) -> Vec<String> {
+    let grid = level.dungeon.to_grid();
+    // later entries win, so the player and stairs are drawn over anything else
+    let markers = spawns
+        .iter()
+        .map(|s| {
+            let glyph = match s.kind {
+                "key" => 'k',
+                "npc" => 'n',
+                "gold" => '$',
+                "health" => '!',
+                "stairs_up" => '<',
+                "stairs_down" => '>',
+                _ => '@',
+            };
+            (Vector2Int::new(s.position.0, s.position.1), glyph)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let (min, max) = grid.bounds();
+    (min.y..=max.y)
+        .rev()
+        .map(|y| {
+            let row = (min.x..=max.x)
+                .map(|x| {
+                    let v = Vector2Int::new(x, y);
+                    match markers.get(&v) {
+                        Some(glyph) => *glyph,
+                        None => grid
+                            .get(v)
+                            .copied()
+                            .flatten()
+                            .map_or(' ', |kind| kind.glyph()),
+                    }
+                })
+                .collect::<String>();
+            row.trim_end().to_string()
+        })
+        .collect()
+}
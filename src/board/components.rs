@@ -1,5 +1,6 @@
-use crate::vectors::Vector2Int;
+use crate::vectors::{Grid, Vector2Int, ORTHO_DIRECTIONS};
 use bevy::prelude::*;
+use serde::Deserialize;
 
 #[derive(Component)]
 pub struct Position {
@@ -10,10 +11,176 @@ pub struct Position {
 pub struct Tile {
     pub visible: bool,
     pub seen: bool,
+    pub kind: TileKind,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum TileKind {
+    #[default]
+    Floor,
+    Wall {
+        material: WallMaterial,
+    },
+    Water,
+    Lava,
+    Chasm,
+    // a door frame, the door itself is a child entity with a `Door` component
+    Door {
+        locked: bool,
+    },
+    // looks and acts like a wall until it is found, then becomes a plain door
+    SecretDoor,
+}
+
+/// what a wall is made of, decides how much digging it takes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum WallMaterial {
+    #[default]
+    Rock,
+    // the walls of prefab rooms
+    Reinforced,
+    // the outer edge of the map, keeps everything out of the void
+    Border,
+}
+
+impl WallMaterial {
+    /// dig power it takes to break through, `None` if the wall can't be dug
+    pub fn durability(&self) -> Option<u32> {
+        match self {
+            WallMaterial::Rock => Some(3),
+            WallMaterial::Reinforced => Some(9),
+            WallMaterial::Border => None,
+        }
+    }
+}
+
+/// turns every wall next to the void (or the grid edge) into a border wall
+pub fn mark_border_walls(tiles: &mut Grid<Option<TileKind>>) {
+    let border = tiles
+        .iter()
+        .filter(|(_, kind)| matches!(kind, Some(TileKind::Wall { .. })))
+        .filter(|(v, _)| {
+            ORTHO_DIRECTIONS
+                .iter()
+                .any(|d| tiles.get(*v + *d).copied().flatten().is_none())
+        })
+        .map(|(v, _)| v)
+        .collect::<Vec<_>>();
+    for v in border {
+        tiles.set(
+            v,
+            Some(TileKind::Wall {
+                material: WallMaterial::Border,
+            }),
+        );
+    }
+}
+
+/// what happens to an actor that steps onto a tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileEffect {
+    Damage(u32),
+    // drops the actor to the next level
+    Fall,
+}
+
+impl TileKind {
+    pub fn is_walkable(&self) -> bool {
+        !matches!(self, TileKind::Wall { .. } | TileKind::SecretDoor)
+    }
+
+    pub fn blocks_vision(&self) -> bool {
+        matches!(self, TileKind::Wall { .. } | TileKind::SecretDoor)
+    }
+
+    /// path finding cost of stepping onto the tile
+    pub fn movement_cost(&self) -> u32 {
+        match self {
+            TileKind::Floor | TileKind::Wall { .. } => 1,
+            // opening a door takes a turn of its own
+            TileKind::Water | TileKind::Door { .. } | TileKind::SecretDoor => 2,
+            // hazards are walkable, but npcs should only cross them as a last resort
+            TileKind::Lava | TileKind::Chasm => 20,
+        }
+    }
+
+    /// the floor feature a map glyph stands for, shared by text maps and prefabs
+    pub fn from_glyph(c: char) -> Option<TileKind> {
+        match c {
+            '~' => Some(TileKind::Water),
+            '%' => Some(TileKind::Lava),
+            '_' => Some(TileKind::Chasm),
+            '+' => Some(TileKind::Door { locked: false }),
+            '=' => Some(TileKind::Door { locked: true }),
+            '*' => Some(TileKind::SecretDoor),
+            _ => None,
+        }
+    }
+
+    /// the text map glyph of the tile
+    pub fn glyph(&self) -> char {
+        match self {
+            TileKind::Floor => '.',
+            TileKind::Wall { .. } => '#',
+            TileKind::Water => '~',
+            TileKind::Lava => '%',
+            TileKind::Chasm => '_',
+            TileKind::Door { locked: false } => '+',
+            TileKind::Door { locked: true } => '=',
+            TileKind::SecretDoor => '*',
+        }
+    }
+
+    pub fn on_enter(&self) -> Option<TileEffect> {
+        match self {
+            TileKind::Lava => Some(TileEffect::Damage(3)),
+            TileKind::Chasm => Some(TileEffect::Fall),
+            _ => None,
+        }
+    }
+
+    /// index into the tile atlas
+    pub fn sprite_index(&self) -> usize {
+        match self {
+            TileKind::Floor | TileKind::Door { .. } => 1,
+            TileKind::Wall { .. } | TileKind::SecretDoor => 49,
+            TileKind::Water => 253,
+            TileKind::Lava => 894,
+            TileKind::Chasm => 801,
+        }
+    }
 }
 
 #[derive(Component)]
-pub struct Wall;
+pub struct Wall {
+    // dig power still needed to break the wall, `None` if it can't be dug
+    pub durability: Option<u32>,
+}
+
+/// closed doors also carry `Occupier` and `VisionBlocker`
+#[derive(Component)]
+pub struct Door {
+    pub open: bool,
+    pub locked: bool,
+}
+
+impl Door {
+    /// index into the tile atlas
+    pub fn sprite_index(&self) -> usize {
+        match (self.open, self.locked) {
+            (true, _) => 447,
+            (false, true) => 441,
+            (false, false) => 444,
+        }
+    }
+}
+
+/// the hidden side of a secret door, also carries `Occupier` and `VisionBlocker`
+#[derive(Component, Default)]
+pub struct SecretDoor {
+    // turns the player spent right next to it
+    pub noticed: u32,
+}
 
 #[derive(Component)]
 pub struct VisionBlocker;
@@ -1,14 +1,25 @@
-use std::{cell::RefCell, collections::HashSet};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashSet},
+};
+
+use rand::rngs::StdRng;
 
 use crate::vectors::Vector2Int;
 
 use super::{
-    room::{Room, RoomGenerator},
+    room::{PrefabRoom, Room, RoomGenerator, RoomRole},
     tunneler::Tunneler,
 };
 
 pub struct Area {
     pub rooms: Vec<Box<dyn Room>>,
+    // indexes of the rooms that were stamped from a prefab
+    pub prefabs: Vec<usize>,
+    // the role of every room, by index
+    pub roles: Vec<RoomRole>,
+    // pairs of room indexes joined by a tunnel
+    pub connections: Vec<(usize, usize)>,
     pub paths: RefCell<Vec<Vec<Vector2Int>>>,
     pub tunneler: Box<dyn Tunneler>,
     pub room_generator: Box<dyn RoomGenerator>,
@@ -17,6 +28,9 @@ impl Area {
     pub fn new(tunneler: Box<dyn Tunneler>, room_generator: Box<dyn RoomGenerator>) -> Self {
         Area {
             rooms: Vec::new(),
+            prefabs: Vec::new(),
+            roles: Vec::new(),
+            connections: Vec::new(),
             paths: RefCell::new(Vec::new()),
             tunneler,
             room_generator,
@@ -71,16 +85,32 @@ impl Area {
         }
     }
 
-    pub fn join_rooms(&self, a: &dyn Room, b: &dyn Room) -> Vec<Vector2Int> {
-        self.tunneler.connect(a.random_point(), b.random_point())
+    pub fn join_rooms(
+        &self,
+        a: &dyn Room,
+        b: &dyn Room,
+        obstacles: &HashSet<Vector2Int>,
+        rng: &mut StdRng,
+    ) -> Vec<Vector2Int> {
+        self.tunneler
+            .connect(a.random_point(rng), b.random_point(rng), obstacles, rng)
+    }
+
+    // tiles of every room but the one with the given index
+    fn room_tiles_except(&self, skip: usize) -> impl Iterator<Item = Vector2Int> + '_ {
+        self.rooms
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| *i != skip)
+            .flat_map(|(_, r)| r.to_tiles())
     }
 
-    fn find_closest_room_pair<'a>(&'a self, other: &'a Area) -> (&'a dyn Room, &'a dyn Room) {
+    fn find_closest_room_pair(&self, other: &Area) -> (usize, usize) {
         // find closest room pair between two areas
         // based on corner distances only
         let mut pairs = Vec::new();
-        for ra in self.rooms.iter() {
-            for rb in other.rooms.iter() {
+        for (ia, ra) in self.rooms.iter().enumerate() {
+            for (ib, rb) in other.rooms.iter().enumerate() {
                 // find min corner dist
                 let d = ra
                     .corners()
@@ -93,7 +123,7 @@ impl Area {
                     })
                     .min()
                     .unwrap();
-                pairs.push((d, &**ra, &**rb));
+                pairs.push((d, ia, ib));
             }
         }
         // sort by corner dist
@@ -101,27 +131,49 @@ impl Area {
         (pairs[0].1, pairs[0].2)
     }
 
-    pub fn join_area(&self, other: &Area) -> Vec<Vector2Int> {
+    /// returns the indexes of the joined rooms in self and other, and the tunnel
+    pub fn join_area(&self, other: &Area, rng: &mut StdRng) -> ((usize, usize), Vec<Vector2Int>) {
         let (room_self, room_other) = self.find_closest_room_pair(other);
-        let path = self.join_rooms(room_self, room_other);
+        let obstacles = self
+            .room_tiles_except(room_self)
+            .chain(other.room_tiles_except(room_other))
+            .collect();
+        let path = self.join_rooms(
+            &*self.rooms[room_self],
+            &*other.rooms[room_other],
+            &obstacles,
+            rng,
+        );
 
         // Update self's paths and walls
         self.paths.borrow_mut().push(path.clone());
-        path
+        ((room_self, room_other), path)
     }
 
-    pub fn generate_rooms(&mut self) {
-        let result = self.room_generator.generate();
+    pub fn generate_rooms(&mut self, rng: &mut StdRng) {
+        let result = self.room_generator.generate(rng);
         self.rooms = result.rooms;
+        self.prefabs = result.prefabs;
+        self.roles = result.roles;
+        self.connections = result.connections;
 
         self.paths.borrow_mut().clear();
-        for connection in &result.connections {
-            let path = self.join_rooms(&*self.rooms[connection.0], &*self.rooms[connection.1]);
+        for (a, b) in self.connections.iter().copied() {
+            let target = self.rooms[b].to_tiles();
+            let obstacles = self
+                .room_tiles_except(a)
+                .filter(|v| !target.contains(v))
+                .collect();
+            let path = self.join_rooms(&*self.rooms[a], &*self.rooms[b], &obstacles, rng);
             self.paths.borrow_mut().push(path);
         }
     }
 
-    pub fn to_tiles(&self) -> HashSet<Vector2Int> {
+    pub fn prefab_rooms(&self) -> impl Iterator<Item = &PrefabRoom> {
+        self.prefabs.iter().filter_map(|i| self.rooms[*i].prefab())
+    }
+
+    pub fn to_tiles(&self) -> BTreeSet<Vector2Int> {
         self.rooms
             .iter()
             .flat_map(|r| r.to_tiles())
@@ -0,0 +1,149 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::vectors::{Vector2Int, ORTHO_DIRECTIONS};
+
+/// returns every floor tile that can be reached from `start` by orthogonal steps
+pub fn flood_fill(start: Vector2Int, floor: &HashSet<Vector2Int>) -> HashSet<Vector2Int> {
+    let mut reached = HashSet::new();
+    if !floor.contains(&start) {
+        return reached;
+    }
+    let mut queue = VecDeque::from([start]);
+    reached.insert(start);
+    while let Some(v) = queue.pop_front() {
+        for dir in ORTHO_DIRECTIONS {
+            let n = v + dir;
+            if floor.contains(&n) && reached.insert(n) {
+                queue.push_back(n);
+            }
+        }
+    }
+    reached
+}
+
+/// splits the floor into connected regions
+///
+/// regions are ordered by their smallest tile, so the output is stable for a given floor
+pub fn find_regions(floor: &HashSet<Vector2Int>) -> Vec<Vec<Vector2Int>> {
+    let mut sorted = floor.iter().copied().collect::<Vec<_>>();
+    sorted.sort();
+
+    let mut assigned = HashSet::new();
+    let mut regions = Vec::new();
+    for v in sorted {
+        if assigned.contains(&v) {
+            continue;
+        }
+        let mut region = flood_fill(v, floor).into_iter().collect::<Vec<_>>();
+        region.sort();
+        assigned.extend(region.iter().copied());
+        regions.push(region);
+    }
+    regions
+}
+
+/// returns the floor tiles that can not be reached from `start`
+///
+/// an empty result means the whole floor is connected
+pub fn unreachable_tiles(start: Vector2Int, floor: &HashSet<Vector2Int>) -> Vec<Vector2Int> {
+    let reached = flood_fill(start, floor);
+    let mut unreachable = floor.difference(&reached).copied().collect::<Vec<_>>();
+    unreachable.sort();
+    unreachable
+}
+
+/// finds the shortest orthogonal tunnel from any tile of `from` to any tile of `to`,
+/// digging only within the inclusive `bounds` and never through `solid` tiles
+///
+/// the returned path starts on a `from` tile and ends on a `to` tile
+pub fn shortest_tunnel(
+    from: &[Vector2Int],
+    to: &HashSet<Vector2Int>,
+    solid: &HashSet<Vector2Int>,
+    bounds: (Vector2Int, Vector2Int),
+) -> Option<Vec<Vector2Int>> {
+    let in_bounds = |v: Vector2Int| {
+        v.x >= bounds.0.x && v.y >= bounds.0.y && v.x <= bounds.1.x && v.y <= bounds.1.y
+    };
+
+    let mut came_from = HashMap::new();
+    let mut queue = VecDeque::new();
+    for v in from {
+        came_from.insert(*v, None);
+        queue.push_back(*v);
+    }
+
+    while let Some(v) = queue.pop_front() {
+        if to.contains(&v) {
+            let mut path = vec![v];
+            let mut cur = v;
+            while let Some(Some(prev)) = came_from.get(&cur) {
+                path.push(*prev);
+                cur = *prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for dir in ORTHO_DIRECTIONS {
+            let n = v + dir;
+            if in_bounds(n) && !solid.contains(&n) && !came_from.contains_key(&n) {
+                came_from.insert(n, Some(v));
+                queue.push_back(n);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::board::recipe::DungeonRecipe;
+
+    fn floor(tiles: &[(i32, i32)]) -> HashSet<Vector2Int> {
+        tiles.iter().map(|(x, y)| Vector2Int::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn finds_unreachable_tiles() {
+        let floor = floor(&[(0, 0), (1, 0), (3, 0), (4, 0)]);
+        let unreachable = unreachable_tiles(Vector2Int::new(0, 0), &floor);
+        assert_eq!(
+            unreachable,
+            vec![Vector2Int::new(3, 0), Vector2Int::new(4, 0)]
+        );
+        assert_eq!(find_regions(&floor).len(), 2);
+    }
+
+    #[test]
+    fn tunnels_around_solid_tiles() {
+        let to = floor(&[(4, 0)]);
+        let solid = floor(&[(2, 0)]);
+        let bounds = (Vector2Int::new(0, -1), Vector2Int::new(4, 1));
+        let path = shortest_tunnel(&[Vector2Int::new(0, 0)], &to, &solid, bounds).unwrap();
+        assert_eq!(path.first(), Some(&Vector2Int::new(0, 0)));
+        assert_eq!(path.last(), Some(&Vector2Int::new(4, 0)));
+        assert!(!path.contains(&Vector2Int::new(2, 0)));
+        assert_eq!(path.len(), 7);
+    }
+
+    #[test]
+    fn no_tunnel_out_of_bounds() {
+        let to = floor(&[(4, 0)]);
+        let solid = floor(&[(2, 0)]);
+        let bounds = (Vector2Int::new(0, 0), Vector2Int::new(4, 0));
+        assert!(shortest_tunnel(&[Vector2Int::new(0, 0)], &to, &solid, bounds).is_none());
+    }
+
+    #[test]
+    fn generated_floors_are_connected() {
+        let recipe = DungeonRecipe::default();
+        for seed in 0..200 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let dungeon = recipe.build(seed as u32 % 5, &mut rng);
+            assert!(dungeon.is_connected(), "seed {} is not connected", seed);
+        }
+    }
+}
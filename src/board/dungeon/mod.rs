@@ -1,12 +1,19 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
-use crate::vectors::Vector2Int;
+use rand::{prelude::*, rngs::StdRng};
+
+use crate::{
+    board::components::{mark_border_walls, TileKind, WallMaterial},
+    vectors::{Grid, Vector2Int, ORTHO_DIRECTIONS},
+};
 
 mod area;
+pub mod connectivity;
 pub mod room;
 pub mod tunneler;
 
 pub use area::Area;
+use room::{PrefabSpawn, Room, RoomKind, RoomRole};
 
 const AREA_SPACING: i32 = 0;
 
@@ -15,7 +22,30 @@ pub struct Dungeon {
     // the gird contains indexes to the areas vec
     // rows / columns
     grid: Vec<Vec<usize>>,
-    pub walls: HashSet<Vector2Int>,
+    // ordered, like the features, so iterating them is the same for every run of a seed
+    pub walls: BTreeSet<Vector2Int>,
+    // tunnels dug after generation to join otherwise unreachable regions
+    pub paths: Vec<Vec<Vector2Int>>,
+    // floor tiles that are something other than plain floor
+    pub features: BTreeMap<Vector2Int, TileKind>,
+    // empty tiles left between neighbouring areas
+    pub area_spacing: i32,
+    // rooms of different areas joined by a tunnel, as (area, room) indexes
+    links: Vec<((usize, usize), (usize, usize))>,
+}
+
+/// a room of the finished dungeon and what is known about it
+#[derive(Clone)]
+pub struct RoomInfo {
+    // rooms are numbered area by area, so ids are stable for a given seed
+    pub id: usize,
+    pub area: usize,
+    pub role: RoomRole,
+    pub centre: Vector2Int,
+    // sorted
+    pub tiles: Vec<Vector2Int>,
+    // ids of the rooms this one has a tunnel to
+    pub neighbours: Vec<usize>,
 }
 
 impl Dungeon {
@@ -24,7 +54,11 @@ impl Dungeon {
         Dungeon {
             areas: Vec::new(),
             grid,
-            walls: HashSet::new(),
+            walls: BTreeSet::new(),
+            paths: Vec::new(),
+            features: BTreeMap::new(),
+            area_spacing: AREA_SPACING,
+            links: Vec::new(),
         }
     }
 
@@ -36,23 +70,329 @@ impl Dungeon {
         self.grid[idx % row_count].push(idx);
     }
 
-    pub fn generate(&mut self) {
+    pub fn generate(&mut self, rng: &mut StdRng) {
         for area in self.areas.iter_mut() {
-            area.generate_rooms();
+            area.generate_rooms(rng);
         }
         self.position_areas();
-        self.connect_areas();
+        self.connect_areas(rng);
+        self.connect_regions();
         self.add_walls();
+        let prefab_features = self
+            .prefab_rooms()
+            .flat_map(|p| p.features())
+            .collect::<Vec<_>>();
+        self.features.extend(prefab_features);
     }
 
-    pub fn to_tiles(&self) -> HashSet<Vector2Int> {
+    pub fn to_tiles(&self) -> BTreeSet<Vector2Int> {
+        self.floor_tiles()
+            .into_iter()
+            .chain(self.walls.iter().copied())
+            .collect()
+    }
+
+    /// every generated tile with its kind, `None` outside of the dungeon
+    pub fn to_grid(&self) -> Grid<Option<TileKind>> {
+        let mut grid = Grid::covering(self.to_tiles(), None);
+        for v in self.floor_tiles() {
+            let kind = self.features.get(&v).copied().unwrap_or_default();
+            grid.set(v, Some(kind));
+        }
+        let reinforced = self.prefab_walls();
+        for v in self.walls.iter() {
+            let material = match reinforced.contains(v) {
+                true => WallMaterial::Reinforced,
+                false => WallMaterial::Rock,
+            };
+            grid.set(*v, Some(TileKind::Wall { material }));
+        }
+        mark_border_walls(&mut grid);
+        grid
+    }
+
+    /// scatters small pools of the given kind inside of random rooms
+    ///
+    /// pools are kept off room edges, so corridors always lead onto plain floor
+    pub fn add_pools(&mut self, kind: TileKind, count: u32, rng: &mut StdRng) {
+        for _ in 0..count {
+            let Some(area) = self.areas.choose(rng) else {
+                return;
+            };
+            let Some(room) = area.rooms.choose(rng) else {
+                continue;
+            };
+            // prefabs keep the layout they were drawn with
+            if room.kind() == RoomKind::Vault {
+                continue;
+            }
+            let room_tiles = room.to_tiles();
+            let centre = room.random_point(rng);
+            let radius = rng.gen_range(1..=2);
+            for v in centre.circle_area(radius) {
+                let inner = (-1..=1)
+                    .flat_map(|y| (-1..=1).map(move |x| Vector2Int::new(x, y)))
+                    .all(|d| room_tiles.contains(&(v + d)));
+                if inner {
+                    self.features.insert(v, kind);
+                }
+            }
+        }
+    }
+
+    /// places doors on corridor tiles that lead straight into a room
+    ///
+    /// only one tile wide entrances get a door, so a room can't be bypassed around it
+    pub fn add_doors(&mut self, door_chance: f64, locked_chance: f64, rng: &mut StdRng) {
+        let floor = self.floor_tiles();
+        let room_tiles = self
+            .areas
+            .iter()
+            .flat_map(|a| a.rooms.iter().flat_map(|r| r.to_tiles()))
+            .collect::<HashSet<_>>();
+
+        let mut candidates = floor
+            .iter()
+            .filter(|v| !room_tiles.contains(v) && !self.features.contains_key(v))
+            .filter(|v| {
+                ORTHO_DIRECTIONS
+                    .iter()
+                    .any(|d| room_tiles.contains(&(**v + *d)))
+            })
+            .filter(|v| {
+                let open = |d: Vector2Int| floor.contains(&(**v + d));
+                let horizontal = open(Vector2Int::LEFT) && open(Vector2Int::RIGHT);
+                let vertical = open(Vector2Int::UP) && open(Vector2Int::DOWN);
+                (horizontal && !open(Vector2Int::UP) && !open(Vector2Int::DOWN))
+                    || (vertical && !open(Vector2Int::LEFT) && !open(Vector2Int::RIGHT))
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        for v in candidates {
+            // never put two doors next to each other
+            let crowded = ORTHO_DIRECTIONS
+                .iter()
+                .any(|d| matches!(self.features.get(&(v + *d)), Some(TileKind::Door { .. })));
+            if crowded || !rng.gen_bool(door_chance) {
+                continue;
+            }
+            let locked = rng.gen_bool(locked_chance);
+            self.features.insert(v, TileKind::Door { locked });
+        }
+    }
+
+    /// hides some of the unlocked doors behind a wall
+    ///
+    /// a door is only hidden if the floor stays connected without it,
+    /// so secret doors end up on loops like the extra bubble connections and never lock anything away
+    pub fn add_secret_doors(&mut self, secret_chance: f64, rng: &mut StdRng) {
+        if secret_chance <= 0. {
+            return;
+        }
+        let Some(start) = self.get_valid_spots().first().copied() else {
+            return;
+        };
+        let mut floor = self.floor_tiles();
+        let doors = self
+            .features
+            .iter()
+            .filter(|(_, kind)| matches!(kind, TileKind::Door { locked: false }))
+            .map(|(v, _)| *v)
+            .collect::<Vec<_>>();
+
+        for v in doors {
+            if !rng.gen_bool(secret_chance) {
+                continue;
+            }
+            floor.remove(&v);
+            if !connectivity::unreachable_tiles(start, &floor).is_empty() {
+                // the only way into some part of the floor
+                floor.insert(v);
+                continue;
+            }
+            self.features.insert(v, TileKind::SecretDoor);
+        }
+    }
+
+    /// picks one spot for a key per locked door
+    ///
+    /// the spots can all be reached from the player start without passing a locked door
+    pub fn get_key_spots(&self, rng: &mut StdRng) -> Vec<Vector2Int> {
+        let locked = self
+            .features
+            .iter()
+            .filter(|(_, kind)| matches!(kind, TileKind::Door { locked: true }))
+            .map(|(v, _)| *v)
+            .collect::<HashSet<_>>();
+        let valid_spots = self.get_valid_spots();
+        let Some(start) = valid_spots.first().copied() else {
+            return Vec::new();
+        };
+        let open_floor = self
+            .floor_tiles()
+            .difference(&locked)
+            .copied()
+            .collect::<HashSet<_>>();
+        let reachable = connectivity::flood_fill(start, &open_floor);
+        let spots = valid_spots
+            .into_iter()
+            .filter(|v| *v != start && reachable.contains(v))
+            .collect::<Vec<_>>();
+        spots.choose_multiple(rng, locked.len()).copied().collect()
+    }
+
+    /// the kind of room every floor tile belongs to
+    pub fn get_room_kinds(&self) -> HashMap<Vector2Int, RoomKind> {
+        let mut kinds = self
+            .floor_tiles()
+            .into_iter()
+            .map(|v| (v, RoomKind::Corridor))
+            .collect::<HashMap<_, _>>();
+        for room in self.areas.iter().flat_map(|a| a.rooms.iter()) {
+            for v in room.to_tiles() {
+                kinds.insert(v, room.kind());
+            }
+        }
+        kinds
+    }
+
+    /// every room with its id, role and neighbours
+    ///
+    /// the room holding the player start becomes the start room
+    /// and the common room furthest away from it (in tunnels) the exit
+    pub fn get_rooms(&self) -> Vec<RoomInfo> {
+        // ids of the first room of every area
+        let first_ids = self
+            .areas
+            .iter()
+            .scan(0, |next, area| {
+                let first = *next;
+                *next += area.rooms.len();
+                Some(first)
+            })
+            .collect::<Vec<_>>();
+
+        let mut rooms = Vec::new();
+        for (area_idx, area) in self.areas.iter().enumerate() {
+            for (idx, room) in area.rooms.iter().enumerate() {
+                let mut tiles = room.to_tiles().into_iter().collect::<Vec<_>>();
+                tiles.sort();
+                rooms.push(RoomInfo {
+                    id: first_ids[area_idx] + idx,
+                    area: area_idx,
+                    role: area.roles.get(idx).copied().unwrap_or_default(),
+                    centre: room.centre(),
+                    tiles,
+                    neighbours: Vec::new(),
+                });
+            }
+        }
+
+        let inner = self.areas.iter().enumerate().flat_map(|(area_idx, area)| {
+            area.connections
+                .iter()
+                .map(move |(a, b)| ((area_idx, *a), (area_idx, *b)))
+        });
+        for ((area_a, a), (area_b, b)) in inner.chain(self.links.iter().copied()) {
+            let (a, b) = (first_ids[area_a] + a, first_ids[area_b] + b);
+            if a == b || rooms[a].neighbours.contains(&b) {
+                continue;
+            }
+            rooms[a].neighbours.push(b);
+            rooms[b].neighbours.push(a);
+        }
+        for room in rooms.iter_mut() {
+            room.neighbours.sort();
+        }
+
+        let Some(start) = self.get_valid_spots().first().copied() else {
+            return rooms;
+        };
+        // the start can be on a corridor, then the closest room counts
+        let Some(start_id) = rooms
+            .iter()
+            .filter(|r| r.role == RoomRole::Common)
+            .min_by_key(|r| match r.tiles.binary_search(&start) {
+                Ok(_) => 0,
+                Err(_) => 1 + r.centre.manhattan(start),
+            })
+            .map(|r| r.id)
+        else {
+            return rooms;
+        };
+        rooms[start_id].role = RoomRole::Start;
+
+        // breadth first over the room graph,
+        // ties go to rooms in another area and then to the ones further away
+        let mut hops = vec![None; rooms.len()];
+        hops[start_id] = Some(0);
+        let mut queue = VecDeque::from([start_id]);
+        while let Some(id) = queue.pop_front() {
+            for n in rooms[id].neighbours.iter() {
+                if hops[*n].is_none() {
+                    hops[*n] = hops[id].map(|h| h + 1);
+                    queue.push_back(*n);
+                }
+            }
+        }
+        let (start_area, start_centre) = (rooms[start_id].area, rooms[start_id].centre);
+        let exit = rooms
+            .iter()
+            .filter(|r| r.role == RoomRole::Common)
+            .max_by_key(|r| {
+                (
+                    hops[r.id],
+                    r.area != start_area,
+                    r.centre.manhattan(start_centre),
+                    r.id,
+                )
+            })
+            .map(|r| r.id);
+        if let Some(exit) = exit {
+            rooms[exit].role = RoomRole::Exit;
+        }
+        rooms
+    }
+
+    /// every walkable tile, rooms and corridors alike
+    ///
+    /// meant for lookups, anything that depends on the order has to sort the tiles first
+    pub fn floor_tiles(&self) -> HashSet<Vector2Int> {
+        let solid = self.prefab_walls();
         self.areas
             .iter()
             .flat_map(|a| a.to_tiles())
-            .chain(self.walls.iter().copied())
+            .chain(self.paths.iter().flatten().copied())
+            .filter(|v| !solid.contains(v))
+            .collect()
+    }
+
+    pub fn prefab_rooms(&self) -> impl Iterator<Item = &room::PrefabRoom> {
+        self.areas.iter().flat_map(|a| a.prefab_rooms())
+    }
+
+    /// the marker spawns of every placed prefab
+    pub fn get_prefab_spawns(&self) -> Vec<(Vector2Int, PrefabSpawn)> {
+        self.prefab_rooms()
+            .flat_map(|p| p.spawns().iter().copied())
             .collect()
     }
 
+    // prefab walls stay solid even where tunnels were dug through them
+    fn prefab_walls(&self) -> HashSet<Vector2Int> {
+        self.prefab_rooms().flat_map(|p| p.walls()).collect()
+    }
+
+    /// true if every floor tile can be reached from the first valid spot (the player start)
+    pub fn is_connected(&self) -> bool {
+        let Some(start) = self.get_valid_spots().first().copied() else {
+            return false;
+        };
+        connectivity::unreachable_tiles(start, &self.floor_tiles()).is_empty()
+    }
+
     fn position_areas(&mut self) {
         let column_count = self.grid[0].len();
 
@@ -63,17 +403,19 @@ impl Dungeon {
                     .iter()
                     .map(|r| match r.get(i) {
                         None => 0,
-                        Some(_a) => self.areas[i].get_size().x,
+                        Some(a) => self.areas[*a].get_size().x,
                     })
                     .max()
                     .unwrap()
-                    + AREA_SPACING
+                    + self.area_spacing
             })
             .collect::<Vec<_>>();
         let row_heights = self
             .grid
             .iter()
-            .map(|r| r.iter().map(|i| self.areas[*i].get_size().y).max().unwrap() + AREA_SPACING)
+            .map(|r| {
+                r.iter().map(|i| self.areas[*i].get_size().y).max().unwrap() + self.area_spacing
+            })
             .collect::<Vec<_>>();
 
         // calculate the offset amounts per each grid position
@@ -93,11 +435,23 @@ impl Dungeon {
         }
     }
 
+    /// plain floor outside of prefabs, prefabs are populated from their own markers
     pub fn get_valid_spots(&self) -> Vec<Vector2Int> {
-        self.areas.iter().flat_map(|a| a.to_tiles()).collect()
+        let prefab_tiles = self
+            .prefab_rooms()
+            .flat_map(|p| p.to_tiles())
+            .collect::<HashSet<_>>();
+        let mut spots = self
+            .floor_tiles()
+            .into_iter()
+            .filter(|v| !self.features.contains_key(v) && !prefab_tiles.contains(v))
+            .collect::<Vec<_>>();
+        // hash set ordering differs between runs, sort so that seeded spawning is reproducible
+        spots.sort();
+        spots
     }
 
-    fn connect_areas(&mut self) {
+    fn connect_areas(&mut self, rng: &mut StdRng) {
         // connect areas based on their grid location
         let mut pairs = Vec::new();
         for (y, row) in self.grid.iter().enumerate() {
@@ -113,8 +467,37 @@ impl Dungeon {
             }
         }
         for pair in pairs {
-            let path = self.areas[*pair.0].join_area(&self.areas[pair.1]);
+            let (rooms, path) = self.areas[*pair.0].join_area(&self.areas[pair.1], rng);
             self.areas[*pair.0].paths.borrow_mut().push(path);
+            self.links.push(((*pair.0, rooms.0), (pair.1, rooms.1)));
+        }
+    }
+
+    fn connect_regions(&mut self) {
+        // nothing in the room generators or tunnelers guarantees a single connected floor,
+        // so dig the shortest passage from every region that can't be reached from the start
+        // prefab walls can sit right on the edge, so allow digging one tile around them
+        let (min, max) = self.get_bounds();
+        let bounds = (min - Vector2Int::new(1, 1), max + Vector2Int::new(1, 1));
+        let mut floor = self.floor_tiles();
+        let solid = self.prefab_walls();
+        let regions = connectivity::find_regions(&floor);
+        let Some(start) = regions.first().map(|r| r[0]) else {
+            return;
+        };
+
+        let mut reached = connectivity::flood_fill(start, &floor);
+        for region in regions.iter().skip(1) {
+            if reached.contains(&region[0]) {
+                // already joined by an earlier tunnel
+                continue;
+            }
+            let Some(path) = connectivity::shortest_tunnel(region, &reached, &solid, bounds) else {
+                continue;
+            };
+            floor.extend(path.iter().copied());
+            self.paths.push(path);
+            reached = connectivity::flood_fill(start, &floor);
         }
     }
 
@@ -132,15 +515,38 @@ impl Dungeon {
     }
 
     fn add_walls(&mut self) {
-        let (min, max) = self.get_bounds();
-        for x in min.x - 1..=max.x + 1 {
-            for y in min.y - 1..=max.y + 1 {
-                let point = Vector2Int::new(x, y);
-                let is_inside = self.areas.iter().any(|a| a.to_tiles().contains(&point));
-                if !is_inside {
-                    self.walls.insert(point);
-                }
-            }
+        let tiles = self.floor_tiles();
+        // tunnels may leave the area bounds, so cover the floor itself
+        let (min, max) = Grid::covering(tiles.iter().copied(), ()).bounds();
+        let border = Vector2Int::new(1, 1);
+        // mark the floor on a grid with a one tile border, everything else becomes a wall
+        let mut floor = Grid::new(min - border, max + border, false);
+        for v in tiles {
+            floor.set(v, true);
+        }
+        self.walls = floor
+            .iter()
+            .filter(|(_, is_floor)| !**is_floor)
+            .map(|(v, _)| v)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::board::recipe::DungeonRecipe;
+
+    #[test]
+    fn same_seed_same_dungeon() {
+        let recipe = DungeonRecipe::default();
+        for seed in 0..20 {
+            let a = recipe.build(1, &mut StdRng::seed_from_u64(seed));
+            let b = recipe.build(1, &mut StdRng::seed_from_u64(seed));
+            let (a_grid, b_grid) = (a.to_grid(), b.to_grid());
+            assert!(a_grid.iter().eq(b_grid.iter()), "seed {} differs", seed);
+            assert_eq!(a.get_valid_spots(), b.get_valid_spots());
         }
     }
 }
@@ -0,0 +1,141 @@
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
+
+use crate::vectors::Vector2Int;
+
+use super::{pick_prefab, square_room::SquareRoom, GeneratorResult, Prefab, Room, RoomGenerator};
+
+#[derive(Clone, Deserialize)]
+pub struct BspGenerator {
+    // size of the rectangle that gets partitioned (in tiles)
+    pub size: (u32, u32),
+    // a leaf is only split if both halves would be at least this big,
+    // and big enough for a `min_room_size` room with its padding
+    pub min_leaf_size: u32,
+    // max number of recursive splits
+    pub max_depth: u32,
+    // min room size (in tiles)
+    pub min_room_size: u32,
+    // min distance between a room and the edge of its leaf
+    pub room_padding: u32,
+    // templates that may replace a leaf's room when they fit into the leaf
+    #[serde(default)]
+    pub prefabs: Vec<Prefab>,
+}
+
+impl BspGenerator {
+    // rooms need at least 3 tiles for a random interior point
+    fn smallest_room(&self) -> i32 {
+        (self.min_room_size as i32).max(3)
+    }
+
+    // recursively partitions the rect between a and b (inclusive)
+    // returns the indexes of all rooms placed inside of it
+    fn split(
+        &self,
+        a: Vector2Int,
+        b: Vector2Int,
+        depth: u32,
+        rng: &mut StdRng,
+        result: &mut GeneratorResult,
+    ) -> Vec<usize> {
+        let size = b - a + Vector2Int::new(1, 1);
+        // a leaf has to fit the smallest room with its padding
+        let min =
+            (self.min_leaf_size as i32).max(self.smallest_room() + 2 * self.room_padding as i32);
+        let can_split_x = size.x >= 2 * min;
+        let can_split_y = size.y >= 2 * min;
+
+        if depth >= self.max_depth || (!can_split_x && !can_split_y) {
+            result.rooms.push(self.leaf_room(a, b, rng));
+            return vec![result.rooms.len() - 1];
+        }
+
+        // prefer cutting across the longer side to avoid long thin leaves
+        let split_x = match (can_split_x, can_split_y) {
+            (true, false) => true,
+            (false, true) => false,
+            _ if size.x > size.y => true,
+            _ if size.y > size.x => false,
+            _ => rng.gen_bool(0.5),
+        };
+
+        let (first, second) = if split_x {
+            let x = rng.gen_range(a.x + min..=b.x + 1 - min);
+            (
+                self.split(a, Vector2Int::new(x - 1, b.y), depth + 1, rng, result),
+                self.split(Vector2Int::new(x, a.y), b, depth + 1, rng, result),
+            )
+        } else {
+            let y = rng.gen_range(a.y + min..=b.y + 1 - min);
+            (
+                self.split(a, Vector2Int::new(b.x, y - 1), depth + 1, rng, result),
+                self.split(Vector2Int::new(a.x, y), b, depth + 1, rng, result),
+            )
+        };
+
+        // join the two siblings through their closest rooms
+        let connection = first
+            .iter()
+            .flat_map(|i| second.iter().map(move |j| (*i, *j)))
+            .min_by_key(|(i, j)| {
+                result.rooms[*i]
+                    .centre()
+                    .manhattan(result.rooms[*j].centre())
+            })
+            .unwrap();
+        result.connections.push(connection);
+
+        [first, second].concat()
+    }
+
+    fn leaf_room(&self, a: Vector2Int, b: Vector2Int, rng: &mut StdRng) -> Box<dyn Room> {
+        let padding = self.room_padding as i32;
+        if let Some(mut prefab) = pick_prefab(&self.prefabs, rng) {
+            let size = prefab.corners()[2] + Vector2Int::new(1, 1);
+            let free_x = b.x - a.x + 1 - 2 * padding - size.x;
+            let free_y = b.y - a.y + 1 - 2 * padding - size.y;
+            // prefabs that don't fit into the leaf are skipped
+            if free_x >= 0 && free_y >= 0 {
+                prefab.shift(Vector2Int::new(
+                    a.x + padding + rng.gen_range(0..=free_x),
+                    a.y + padding + rng.gen_range(0..=free_y),
+                ));
+                return Box::new(prefab);
+            }
+        }
+        // the space left for the room, splitting keeps it at least `min_room_size`
+        let max_w = b.x - a.x + 1 - 2 * padding;
+        let max_h = b.y - a.y + 1 - 2 * padding;
+        let min_size = self.smallest_room();
+
+        let w = rng.gen_range(min_size..=max_w);
+        let h = rng.gen_range(min_size..=max_h);
+
+        let x = rng.gen_range(a.x + padding..=b.x - padding - w + 1);
+        let y = rng.gen_range(a.y + padding..=b.y - padding - h + 1);
+
+        Box::new(SquareRoom::new(
+            Vector2Int::new(x, y),
+            Vector2Int::new(x + w - 1, y + h - 1),
+        ))
+    }
+}
+
+impl RoomGenerator for BspGenerator {
+    fn validate(&self) -> Result<(), String> {
+        let min = self.smallest_room() + 2 * self.room_padding as i32;
+        if (self.size.0 as i32) < min || (self.size.1 as i32) < min {
+            return Err(format!("bsp size has to fit a room of {} tiles", min));
+        }
+        Ok(())
+    }
+
+    fn generate(&self, rng: &mut StdRng) -> GeneratorResult {
+        let mut result = GeneratorResult::new(Vec::new(), Vec::new());
+        let max = Vector2Int::new(self.size.0 as i32 - 1, self.size.1 as i32 - 1);
+        self.split(Vector2Int::new(0, 0), max, 0, rng, &mut result);
+        // tag the finished rooms
+        GeneratorResult::new(result.rooms, result.connections)
+    }
+}
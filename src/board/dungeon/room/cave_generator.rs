@@ -0,0 +1,181 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
+
+use crate::vectors::{Vector2Int, ORTHO_DIRECTIONS};
+
+use super::{
+    cavern_room::CavernRoom, check_chance, pick_prefab, GeneratorResult, Prefab, PrefabRoom, Room,
+    RoomGenerator,
+};
+
+#[derive(Clone, Deserialize)]
+pub struct CaveGenerator {
+    // size of the noise grid (in tiles)
+    pub size: (u32, u32),
+    // chance of a cell starting out as floor
+    pub floor_chance: f64,
+    // number of cellular automata passes
+    pub smoothing_steps: u32,
+    // pockets with less tiles than this are filled in
+    pub min_cavern_size: usize,
+    // templates that may be carved out of the cave
+    #[serde(default)]
+    pub prefabs: Vec<Prefab>,
+}
+
+impl CaveGenerator {
+    fn noise(&self, rng: &mut StdRng) -> Vec<Vec<bool>> {
+        let (w, h) = (self.size.0 as usize, self.size.1 as usize);
+        (0..h)
+            .map(|y| {
+                (0..w)
+                    .map(|x| {
+                        // keep the border solid
+                        let edge = x == 0 || y == 0 || x == w - 1 || y == h - 1;
+                        !edge && rng.gen_bool(self.floor_chance)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // classic 4-5 rule: a cell becomes a wall when most of its neighbours are walls
+    fn smooth(cells: &[Vec<bool>]) -> Vec<Vec<bool>> {
+        let h = cells.len() as i32;
+        let w = cells[0].len() as i32;
+        let is_wall =
+            |x: i32, y: i32| x < 0 || y < 0 || x >= w || y >= h || !cells[y as usize][x as usize];
+
+        (0..h)
+            .map(|y| {
+                (0..w)
+                    .map(|x| {
+                        let walls = (-1..=1)
+                            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                            .filter(|(dx, dy)| (*dx, *dy) != (0, 0) && is_wall(x + dx, y + dy))
+                            .count();
+                        match walls {
+                            0..=3 => true,
+                            4 => cells[y as usize][x as usize],
+                            _ => false,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // places a prefab inside of the grid and turns its footprint into solid rock,
+    // with a one tile margin so it doesn't merge with the caverns around it
+    fn carve_prefab(&self, cells: &mut [Vec<bool>], rng: &mut StdRng) -> Option<PrefabRoom> {
+        let mut prefab = pick_prefab(&self.prefabs, rng)?;
+        let size = prefab.corners()[2] + Vector2Int::new(1, 1);
+        let free_x = self.size.0 as i32 - 2 - size.x;
+        let free_y = self.size.1 as i32 - 2 - size.y;
+        if free_x < 0 || free_y < 0 {
+            return None;
+        }
+        let min = Vector2Int::new(1 + rng.gen_range(0..=free_x), 1 + rng.gen_range(0..=free_y));
+        prefab.shift(min);
+        for y in min.y - 1..=min.y + size.y {
+            for x in min.x - 1..=min.x + size.x {
+                cells[y as usize][x as usize] = false;
+            }
+        }
+        Some(prefab)
+    }
+
+    // splits the floor cells into orthogonally connected pockets
+    fn pockets(cells: &[Vec<bool>]) -> Vec<Vec<Vector2Int>> {
+        let mut floor = HashSet::new();
+        for (y, row) in cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if *cell {
+                    floor.insert(Vector2Int::new(x as i32, y as i32));
+                }
+            }
+        }
+
+        let mut pockets = Vec::new();
+        let mut visited = HashSet::new();
+        // walk the grid in order so the pocket order is stable
+        for (y, row) in cells.iter().enumerate() {
+            for x in 0..row.len() {
+                let start = Vector2Int::new(x as i32, y as i32);
+                if !floor.contains(&start) || visited.contains(&start) {
+                    continue;
+                }
+                let mut pocket = Vec::new();
+                let mut queue = VecDeque::from([start]);
+                visited.insert(start);
+                while let Some(v) = queue.pop_front() {
+                    pocket.push(v);
+                    for dir in ORTHO_DIRECTIONS {
+                        let n = v + dir;
+                        if floor.contains(&n) && visited.insert(n) {
+                            queue.push_back(n);
+                        }
+                    }
+                }
+                pockets.push(pocket);
+            }
+        }
+        pockets
+    }
+}
+
+impl RoomGenerator for CaveGenerator {
+    fn validate(&self) -> Result<(), String> {
+        // the border is kept solid, so there has to be something inside of it
+        if self.size.0 < 3 || self.size.1 < 3 {
+            return Err("cave size has to be at least 3 tiles".to_string());
+        }
+        check_chance("cave floor_chance", self.floor_chance)
+    }
+
+    fn generate(&self, rng: &mut StdRng) -> GeneratorResult {
+        let mut cells = self.noise(rng);
+        for _ in 0..self.smoothing_steps {
+            cells = Self::smooth(&cells);
+        }
+
+        let prefab = self.carve_prefab(&mut cells, rng);
+
+        let mut pockets = Self::pockets(&cells);
+        let largest = pockets.iter().map(|p| p.len()).max().unwrap_or(0);
+        // cull the small pockets, but always keep at least the biggest one
+        pockets.retain(|p| p.len() >= self.min_cavern_size.min(largest));
+        if pockets.is_empty() {
+            // nothing survived the smoothing, fall back to a tiny chamber in the middle
+            let centre = Vector2Int::new(self.size.0 as i32 / 2, self.size.1 as i32 / 2);
+            pockets.push(
+                (-1..=1)
+                    .flat_map(|y| (-1..=1).map(move |x| centre + Vector2Int::new(x, y)))
+                    .collect(),
+            );
+        }
+
+        let mut rooms = pockets
+            .into_iter()
+            .map(|p| Box::new(CavernRoom::new(p)) as Box<dyn Room>)
+            .collect::<Vec<_>>();
+        if let Some(prefab) = prefab {
+            rooms.push(Box::new(prefab));
+        }
+
+        // link every remaining pocket to the closest one before it,
+        // the area's tunneler then digs the actual passages
+        let connections = (1..rooms.len())
+            .map(|i| {
+                let closest = (0..i)
+                    .min_by_key(|j| rooms[i].centre().manhattan(rooms[*j].centre()))
+                    .unwrap();
+                (closest, i)
+            })
+            .collect();
+
+        GeneratorResult::new(rooms, connections)
+    }
+}
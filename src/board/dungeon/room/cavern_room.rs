@@ -0,0 +1,85 @@
+use std::collections::{BTreeSet, HashSet};
+
+use rand::{rngs::StdRng, Rng};
+
+use crate::vectors::Vector2Int;
+
+use super::{Room, RoomKind};
+
+/// an irregular room made of an arbitrary set of tiles
+pub struct CavernRoom {
+    // ordered so that picking a random tile is reproducible for a given seed
+    tiles: BTreeSet<Vector2Int>,
+    kind: RoomKind,
+}
+
+impl CavernRoom {
+    pub fn new(tiles: impl IntoIterator<Item = Vector2Int>) -> CavernRoom {
+        CavernRoom::with_kind(tiles, RoomKind::Cave)
+    }
+
+    pub fn with_kind(tiles: impl IntoIterator<Item = Vector2Int>, kind: RoomKind) -> CavernRoom {
+        let tiles = tiles.into_iter().collect::<BTreeSet<_>>();
+        assert!(!tiles.is_empty(), "a cavern needs at least one tile");
+        CavernRoom { tiles, kind }
+    }
+}
+
+impl Room for CavernRoom {
+    fn kind(&self) -> RoomKind {
+        self.kind
+    }
+
+    fn random_point(&self, rng: &mut StdRng) -> Vector2Int {
+        let idx = rng.gen_range(0..self.tiles.len());
+        *self.tiles.iter().nth(idx).unwrap()
+    }
+
+    fn to_tiles(&self) -> HashSet<Vector2Int> {
+        self.tiles.iter().copied().collect()
+    }
+
+    fn centre(&self) -> Vector2Int {
+        // the centroid of a cave can lie outside of it,
+        // so use the cavern tile that is closest to it instead
+        let sum = self
+            .tiles
+            .iter()
+            .fold(Vector2Int::new(0, 0), |acc, v| acc + *v);
+        let centroid = sum / self.tiles.len() as i32;
+        *self
+            .tiles
+            .iter()
+            .min_by_key(|v| v.manhattan(centroid))
+            .unwrap()
+    }
+
+    fn corners(&self) -> [Vector2Int; 4] {
+        let min_x = self.tiles.iter().map(|v| v.x).min().unwrap();
+        let max_x = self.tiles.iter().map(|v| v.x).max().unwrap();
+        let min_y = self.tiles.iter().map(|v| v.y).min().unwrap();
+        let max_y = self.tiles.iter().map(|v| v.y).max().unwrap();
+        [
+            Vector2Int::new(min_x, min_y),
+            Vector2Int::new(max_x, min_y),
+            Vector2Int::new(max_x, max_y),
+            Vector2Int::new(min_x, max_y),
+        ]
+    }
+
+    fn intersects(&self, other: &dyn Room, border: Option<u32>) -> bool {
+        let b = border.unwrap_or(0) as i32;
+
+        let other_tiles = other.to_tiles();
+
+        self.tiles.iter().any(|tile| {
+            (tile.y - b..=tile.y + b).any(|y| {
+                (tile.x - b..=tile.x + b).any(|x| other_tiles.contains(&Vector2Int::new(x, y)))
+            })
+        })
+    }
+
+    fn shift(&mut self, offset: Vector2Int) {
+        self.tiles = self.tiles.iter().map(|v| *v + offset).collect();
+    }
+}
@@ -1,4 +1,4 @@
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng};
 
 use crate::vectors::Vector2Int;
 
@@ -16,12 +16,15 @@ impl CircleRoom {
 }
 
 impl Room for CircleRoom {
-    fn random_point(&self) -> Vector2Int {
-        let mut rng = thread_rng();
-
-        let x = rng.gen_range(self.center.x - self.radius..=self.center.x + self.radius);
-        let y = rng.gen_range(self.center.y - self.radius..=self.center.y + self.radius);
-        Vector2Int::new(x, y)
+    fn random_point(&self, rng: &mut StdRng) -> Vector2Int {
+        // sample the bounding square until the point lands inside of the circle
+        loop {
+            let x = rng.gen_range(self.center.x - self.radius..=self.center.x + self.radius);
+            let y = rng.gen_range(self.center.y - self.radius..=self.center.y + self.radius);
+            if (x - self.center.x).pow(2) + (y - self.center.y).pow(2) <= self.radius.pow(2) {
+                return Vector2Int::new(x, y);
+            }
+        }
     }
 
     fn to_tiles(&self) -> std::collections::HashSet<Vector2Int> {
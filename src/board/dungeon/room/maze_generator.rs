@@ -0,0 +1,159 @@
+use std::collections::{BTreeSet, HashSet};
+
+use rand::{prelude::*, rngs::StdRng};
+use serde::Deserialize;
+
+use crate::vectors::{Vector2Int, ORTHO_DIRECTIONS};
+
+use super::{cavern_room::CavernRoom, check_chance, GeneratorResult, RoomGenerator, RoomKind};
+
+/// carves a labyrinth with a recursive backtracker
+///
+/// the maze is returned as a single corridor room
+#[derive(Clone, Deserialize)]
+pub struct MazeGenerator {
+    // size of the maze (in cells)
+    pub size: (u32, u32),
+    // width of the passages (in tiles), cells are separated by one tile of wall
+    #[serde(default)]
+    pub corridor_width: u32,
+    // chance of knocking down any remaining inner wall, adding loops to the perfect maze
+    #[serde(default)]
+    pub loop_chance: f64,
+    // share of dead ends that get opened up, 1.0 leaves none
+    #[serde(default)]
+    pub braid: f64,
+}
+
+impl MazeGenerator {
+    fn in_bounds(&self, cell: Vector2Int) -> bool {
+        cell.x >= 0 && cell.y >= 0 && cell.x < self.size.0 as i32 && cell.y < self.size.1 as i32
+    }
+
+    fn cells(&self) -> impl Iterator<Item = Vector2Int> + '_ {
+        (0..self.size.1 as i32)
+            .flat_map(|y| (0..self.size.0 as i32).map(move |x| Vector2Int::new(x, y)))
+    }
+
+    // visits every cell once, so the passages form a spanning tree
+    fn backtrack(&self, rng: &mut StdRng) -> BTreeSet<(Vector2Int, Vector2Int)> {
+        let mut passages = BTreeSet::new();
+        let start = Vector2Int::new(
+            rng.gen_range(0..self.size.0 as i32),
+            rng.gen_range(0..self.size.1 as i32),
+        );
+        let mut visited = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(cur) = stack.last().copied() {
+            let options = ORTHO_DIRECTIONS
+                .iter()
+                .map(|d| cur + *d)
+                .filter(|n| self.in_bounds(*n) && !visited.contains(n))
+                .collect::<Vec<_>>();
+            match options.choose(rng) {
+                Some(next) => {
+                    visited.insert(*next);
+                    passages.insert(passage(cur, *next));
+                    stack.push(*next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        passages
+    }
+
+    fn add_loops(&self, passages: &mut BTreeSet<(Vector2Int, Vector2Int)>, rng: &mut StdRng) {
+        for cell in self.cells() {
+            // every inner wall is looked at once, from its lower left side
+            for d in [Vector2Int::RIGHT, Vector2Int::UP] {
+                let next = cell + d;
+                if self.in_bounds(next)
+                    && !passages.contains(&(cell, next))
+                    && rng.gen_bool(self.loop_chance)
+                {
+                    passages.insert((cell, next));
+                }
+            }
+        }
+    }
+
+    fn braid(&self, passages: &mut BTreeSet<(Vector2Int, Vector2Int)>, rng: &mut StdRng) {
+        let exits = |cell: Vector2Int, passages: &BTreeSet<_>| {
+            ORTHO_DIRECTIONS
+                .iter()
+                .filter(|d| passages.contains(&passage(cell, cell + **d)))
+                .count()
+        };
+        for cell in self.cells() {
+            if exits(cell, passages) != 1 || !rng.gen_bool(self.braid) {
+                continue;
+            }
+            let closed = ORTHO_DIRECTIONS
+                .iter()
+                .map(|d| cell + *d)
+                .filter(|n| self.in_bounds(*n) && !passages.contains(&passage(cell, *n)))
+                .collect::<Vec<_>>();
+            // joining two dead ends removes both at once
+            let dead_ends = closed
+                .iter()
+                .filter(|n| exits(**n, passages) == 1)
+                .copied()
+                .collect::<Vec<_>>();
+            let target = dead_ends.choose(rng).or_else(|| closed.choose(rng));
+            if let Some(next) = target {
+                passages.insert(passage(cell, *next));
+            }
+        }
+    }
+
+    fn to_tiles(&self, passages: &BTreeSet<(Vector2Int, Vector2Int)>) -> Vec<Vector2Int> {
+        let width = self.corridor_width.max(1) as i32;
+        let pitch = width + 1;
+        let block = |origin: Vector2Int, w: i32, h: i32| {
+            (0..h).flat_map(move |y| (0..w).map(move |x| origin + Vector2Int::new(x, y)))
+        };
+
+        let cells = self
+            .cells()
+            .flat_map(|c| block(Vector2Int::new(c.x * pitch, c.y * pitch), width, width));
+        let openings = passages.iter().flat_map(|(a, b)| {
+            let origin = Vector2Int::new(a.x * pitch, a.y * pitch);
+            match b.x > a.x {
+                true => block(origin + Vector2Int::new(width, 0), 1, width),
+                false => block(origin + Vector2Int::new(0, width), width, 1),
+            }
+        });
+        cells.chain(openings).collect()
+    }
+}
+
+// passages are stored with the lower left cell first
+fn passage(a: Vector2Int, b: Vector2Int) -> (Vector2Int, Vector2Int) {
+    (a.min(b), a.max(b))
+}
+
+impl RoomGenerator for MazeGenerator {
+    fn validate(&self) -> Result<(), String> {
+        // the backtracker starts from a random cell
+        if self.size.0 < 1 || self.size.1 < 1 {
+            return Err("maze size has to be at least 1 cell".to_string());
+        }
+        check_chance("maze loop_chance", self.loop_chance)?;
+        check_chance("maze braid", self.braid)
+    }
+
+    fn generate(&self, rng: &mut StdRng) -> GeneratorResult {
+        let mut passages = self.backtrack(rng);
+        if self.loop_chance > 0. {
+            self.add_loops(&mut passages, rng);
+        }
+        if self.braid > 0. {
+            self.braid(&mut passages, rng);
+        }
+
+        let room = CavernRoom::with_kind(self.to_tiles(&passages), RoomKind::Corridor);
+        GeneratorResult::new(vec![Box::new(room)], Vec::new())
+    }
+}
@@ -1,23 +1,78 @@
+mod bsp_generator;
+mod cave_generator;
+mod cavern_room;
 mod circle_room;
+mod maze_generator;
+mod prefab_room;
 mod square_room;
 
 use std::collections::HashSet;
 
+pub use bsp_generator::BspGenerator;
+pub use cave_generator::CaveGenerator;
 use circle_room::CircleRoom;
-use rand::prelude::*;
+pub use maze_generator::MazeGenerator;
+pub use prefab_room::{pick_prefab, Prefab, PrefabRoom, PrefabSpawn};
+use rand::{prelude::*, rngs::StdRng};
+use serde::Deserialize;
 use square_room::SquareRoom;
 
 use crate::vectors::Vector2Int;
 
 pub trait RoomGenerator {
-    fn generate(&self) -> GeneratorResult;
+    fn generate(&self, rng: &mut StdRng) -> GeneratorResult;
+
+    /// rejects parameters that `generate` can't work with
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// rejects a chance that `gen_bool` would panic on
+pub fn check_chance(name: &str, chance: f64) -> Result<(), String> {
+    match (0. ..=1.).contains(&chance) {
+        true => Ok(()),
+        false => Err(format!(
+            "{} has to be between 0 and 1, got {}",
+            name, chance
+        )),
+    }
 }
 
 pub struct GeneratorResult {
     pub rooms: Vec<Box<dyn Room>>,
     pub connections: Vec<(usize, usize)>,
+    // indexes of the rooms that were stamped from a prefab
+    pub prefabs: Vec<usize>,
+    // the role of every room, by index
+    pub roles: Vec<RoomRole>,
+}
+
+impl GeneratorResult {
+    /// tags the rooms, prefabs keep the role they were drawn with,
+    /// corridor rooms are corridors and everything else is common
+    pub fn new(rooms: Vec<Box<dyn Room>>, connections: Vec<(usize, usize)>) -> Self {
+        let prefabs = (0..rooms.len())
+            .filter(|i| rooms[*i].prefab().is_some())
+            .collect();
+        let roles = rooms
+            .iter()
+            .map(|r| match (r.prefab(), r.kind()) {
+                (Some(prefab), _) => prefab.role(),
+                (None, RoomKind::Corridor) => RoomRole::Corridor,
+                (None, _) => RoomRole::Common,
+            })
+            .collect();
+        GeneratorResult {
+            rooms,
+            connections,
+            prefabs,
+            roles,
+        }
+    }
 }
 
+#[derive(Clone, Deserialize)]
 pub struct BubbleGenerator {
     // bounds for a random room count
     pub room_count: (u32, u32),
@@ -26,18 +81,40 @@ pub struct BubbleGenerator {
     // min distance between rooms
     pub room_padding: Option<u32>,
     pub extra_connection_chance: f64,
+    // templates that may replace any of the rooms
+    #[serde(default)]
+    pub prefabs: Vec<Prefab>,
 }
 impl BubbleGenerator {
-    fn random_dim(&self) -> (i32, i32) {
-        let mut rng = thread_rng();
+    fn random_dim(&self, rng: &mut StdRng) -> (i32, i32) {
         (
             rng.gen_range(self.room_size.0..=self.room_size.1) as i32,
             rng.gen_range(self.room_size.0..=self.room_size.1) as i32,
         )
     }
 
-    fn generate_room(&self, min_corner: Vector2Int, max_corner: Vector2Int) -> Box<dyn Room> {
-        let mut rng = thread_rng();
+    fn generate_room(
+        &self,
+        min_corner: Vector2Int,
+        max_corner: Vector2Int,
+        rng: &mut StdRng,
+    ) -> Box<dyn Room> {
+        if let Some(mut prefab) = pick_prefab(&self.prefabs, rng) {
+            let min = Vector2Int::new(
+                min_corner.x.min(max_corner.x),
+                min_corner.y.min(max_corner.y),
+            );
+            let size = prefab.corners()[2] + Vector2Int::new(1, 1);
+            let free_x = (max_corner.x - min_corner.x).abs() + 1 - size.x;
+            let free_y = (max_corner.y - min_corner.y).abs() + 1 - size.y;
+            // prefabs are kept inside of the room they replace, the ones that don't fit are skipped
+            if free_x >= 0 && free_y >= 0 {
+                prefab.shift(
+                    min + Vector2Int::new(rng.gen_range(0..=free_x), rng.gen_range(0..=free_y)),
+                );
+                return Box::new(prefab);
+            }
+        }
         if rng.gen_bool(0.5) {
             Box::new(SquareRoom::new(min_corner, max_corner))
         } else {
@@ -49,12 +126,25 @@ impl BubbleGenerator {
     }
 }
 impl RoomGenerator for BubbleGenerator {
-    fn generate(&self) -> GeneratorResult {
-        let mut rng = thread_rng();
+    fn validate(&self) -> Result<(), String> {
+        if self.room_count.0 > self.room_count.1 || self.room_size.0 > self.room_size.1 {
+            return Err("bubble room_count and room_size have to be (min, max)".to_string());
+        }
+        // square rooms need an interior for a random point
+        if self.room_size.0 < 2 {
+            return Err("bubble rooms have to be at least 2 tiles big".to_string());
+        }
+        check_chance(
+            "bubble extra_connection_chance",
+            self.extra_connection_chance,
+        )
+    }
+
+    fn generate(&self, rng: &mut StdRng) -> GeneratorResult {
         let mut connections = Vec::new();
 
-        let (w, h) = self.random_dim();
-        let mut rooms = vec![self.generate_room(Vector2Int::new(0, 0), Vector2Int::new(w, h))];
+        let (w, h) = self.random_dim(rng);
+        let mut rooms = vec![self.generate_room(Vector2Int::new(0, 0), Vector2Int::new(w, h), rng)];
         let count = rng.gen_range(self.room_count.0..=self.room_count.1);
 
         let max_dist = self.room_size.1 as i32;
@@ -72,14 +162,14 @@ impl RoomGenerator for BubbleGenerator {
                 );
 
                 // get random room size
-                let (w, h) = self.random_dim();
+                let (w, h) = self.random_dim(rng);
                 // get a second corner in a random direction
                 let b = Vector2Int::new(
-                    a.x + *[-w, w].choose(&mut rng).unwrap(),
-                    a.y + *[-h, h].choose(&mut rng).unwrap(),
+                    a.x + *[-w, w].choose(rng).unwrap(),
+                    a.y + *[-h, h].choose(rng).unwrap(),
                 );
 
-                let new_room = self.generate_room(a, b);
+                let new_room = self.generate_room(a, b, rng);
                 // Check for intersections with existing rooms
                 if rooms
                     .iter()
@@ -104,12 +194,44 @@ impl RoomGenerator for BubbleGenerator {
             }
         }
 
-        GeneratorResult { rooms, connections }
+        GeneratorResult::new(rooms, connections)
     }
 }
 
+/// the broad shape of a room, used to pick what spawns in it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum RoomKind {
+    Chamber,
+    Cave,
+    // floor outside of any room, or a room that is only passages
+    Corridor,
+    // a room stamped from a prefab
+    Vault,
+}
+
+/// what a room is for on its level
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+pub enum RoomRole {
+    #[default]
+    Common,
+    // where the player arrives
+    Start,
+    // holds the stairs down
+    Exit,
+    Treasure,
+    Boss,
+    // a room that is only a passage, like a maze
+    Corridor,
+}
+
 pub trait Room {
-    fn random_point(&self) -> Vector2Int;
+    fn kind(&self) -> RoomKind {
+        RoomKind::Chamber
+    }
+    fn prefab(&self) -> Option<&PrefabRoom> {
+        None
+    }
+    fn random_point(&self, rng: &mut StdRng) -> Vector2Int;
     fn to_tiles(&self) -> HashSet<Vector2Int>;
     fn centre(&self) -> Vector2Int;
     fn corners(&self) -> [Vector2Int; 4];
@@ -0,0 +1,284 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use rand::{prelude::*, rngs::StdRng};
+use serde::Deserialize;
+
+use crate::{
+    board::components::TileKind, pieces::spawn_table::choose_weighted, vectors::Vector2Int,
+};
+
+use super::{Room, RoomKind, RoomRole};
+
+/// a hand-drawn room template that generators can stamp in place of a regular room
+///
+/// glyphs:
+/// - `#` wall, kept solid even when a tunnel runs through it
+/// - `.` floor
+/// - `E` entrance, the floor tile tunnels are dug to
+/// - `n` npc
+/// - `$` gold, worth `gold`
+/// - `!` health drop, healing `health`
+/// - `~ % _ + =` the same floor features as in text maps
+///
+/// any other character (including spaces) is not part of the prefab.
+/// the first row is the top of the room
+#[derive(Clone, Deserialize)]
+pub struct Prefab {
+    // chance of the prefab replacing a room the generator would have placed
+    pub chance: f64,
+    #[serde(default)]
+    pub role: RoomRole,
+    pub rows: Vec<String>,
+    // allow the template to be placed in any of the four orientations
+    #[serde(default)]
+    pub rotate: bool,
+    // allow the template to be flipped horizontally
+    #[serde(default)]
+    pub mirror: bool,
+    // value of every `$` and `!` marker
+    #[serde(default = "marker_value")]
+    pub gold: u32,
+    #[serde(default = "marker_value")]
+    pub health: u32,
+}
+
+fn marker_value() -> u32 {
+    5
+}
+
+/// what a prefab marker spawns once the floor is populated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefabSpawn {
+    Npc,
+    Gold(u32),
+    Health(u32),
+}
+
+/// a placed prefab
+pub struct PrefabRoom {
+    role: RoomRole,
+    // every tile that is not a wall, ordered so picking a random one is reproducible
+    floor: BTreeSet<Vector2Int>,
+    walls: BTreeSet<Vector2Int>,
+    entrances: Vec<Vector2Int>,
+    features: BTreeMap<Vector2Int, TileKind>,
+    spawns: Vec<(Vector2Int, PrefabSpawn)>,
+}
+
+/// picks one of the prefabs, each with its own chance, or none of them
+///
+/// chances that add up to more than 1 are scaled down, then a prefab is always picked.
+/// the room is randomly oriented and has its minimum corner at (0, 0)
+pub fn pick_prefab(prefabs: &[Prefab], rng: &mut StdRng) -> Option<PrefabRoom> {
+    let total = prefabs.iter().map(|p| p.chance.max(0.)).sum::<f64>();
+    if total <= 0. || !rng.gen_bool(total.min(1.)) {
+        return None;
+    }
+    let prefab = choose_weighted(prefabs.iter().map(|p| (p, p.chance)), rng)?;
+    let rotation = match prefab.rotate {
+        true => rng.gen_range(0..4),
+        false => 0,
+    };
+    let mirror = prefab.mirror && rng.gen_bool(0.5);
+    PrefabRoom::new(prefab, rotation, mirror)
+}
+
+impl PrefabRoom {
+    /// `rotation` is the number of quarter turns, returns `None` for a template without floor
+    pub fn new(prefab: &Prefab, rotation: u32, mirror: bool) -> Option<PrefabRoom> {
+        let height = prefab.rows.len() as i32;
+        let mut room = PrefabRoom {
+            role: prefab.role,
+            floor: BTreeSet::new(),
+            walls: BTreeSet::new(),
+            entrances: Vec::new(),
+            features: BTreeMap::new(),
+            spawns: Vec::new(),
+        };
+
+        for (row, line) in prefab.rows.iter().enumerate() {
+            // the board's y axis points up, so flip the rows
+            let y = height - 1 - row as i32;
+            for (x, c) in line.chars().enumerate() {
+                let mut v = Vector2Int::new(x as i32, y);
+                if mirror {
+                    v.x = -v.x;
+                }
+                for _ in 0..rotation {
+                    v = Vector2Int::new(v.y, -v.x);
+                }
+                match c {
+                    '#' => {
+                        room.walls.insert(v);
+                        continue;
+                    }
+                    '.' => (),
+                    'E' => room.entrances.push(v),
+                    'n' => room.spawns.push((v, PrefabSpawn::Npc)),
+                    '$' => room.spawns.push((v, PrefabSpawn::Gold(prefab.gold))),
+                    '!' => room.spawns.push((v, PrefabSpawn::Health(prefab.health))),
+                    _ => match TileKind::from_glyph(c) {
+                        Some(kind) => {
+                            room.features.insert(v, kind);
+                        }
+                        None => continue,
+                    },
+                }
+                room.floor.insert(v);
+            }
+        }
+        if room.floor.is_empty() {
+            return None;
+        }
+
+        // move the minimum corner back to the origin
+        let min = room.corners()[0];
+        room.shift(Vector2Int::new(-min.x, -min.y));
+        Some(room)
+    }
+
+    pub fn role(&self) -> RoomRole {
+        self.role
+    }
+
+    /// tiles that stay walls no matter what gets dug around the room
+    pub fn walls(&self) -> impl Iterator<Item = Vector2Int> + '_ {
+        self.walls.iter().copied()
+    }
+
+    pub fn features(&self) -> impl Iterator<Item = (Vector2Int, TileKind)> + '_ {
+        self.features.iter().map(|(v, kind)| (*v, *kind))
+    }
+
+    pub fn spawns(&self) -> &[(Vector2Int, PrefabSpawn)] {
+        &self.spawns
+    }
+
+    /// floor and walls together
+    fn footprint(&self) -> impl Iterator<Item = Vector2Int> + '_ {
+        self.floor.iter().chain(self.walls.iter()).copied()
+    }
+}
+
+impl Room for PrefabRoom {
+    fn kind(&self) -> RoomKind {
+        RoomKind::Vault
+    }
+
+    fn prefab(&self) -> Option<&PrefabRoom> {
+        Some(self)
+    }
+
+    fn random_point(&self, rng: &mut StdRng) -> Vector2Int {
+        // tunnels should arrive through the entrance, not through the walls
+        if let Some(v) = self.entrances.choose(rng) {
+            return *v;
+        }
+        let idx = rng.gen_range(0..self.floor.len());
+        *self.floor.iter().nth(idx).unwrap()
+    }
+
+    fn to_tiles(&self) -> HashSet<Vector2Int> {
+        self.floor.iter().copied().collect()
+    }
+
+    fn centre(&self) -> Vector2Int {
+        let [a, _, b, _] = self.corners();
+        let middle = Vector2Int::new((a.x + b.x) / 2, (a.y + b.y) / 2);
+        *self
+            .floor
+            .iter()
+            .min_by_key(|v| v.manhattan(middle))
+            .unwrap()
+    }
+
+    fn corners(&self) -> [Vector2Int; 4] {
+        let min_x = self.footprint().map(|v| v.x).min().unwrap();
+        let max_x = self.footprint().map(|v| v.x).max().unwrap();
+        let min_y = self.footprint().map(|v| v.y).min().unwrap();
+        let max_y = self.footprint().map(|v| v.y).max().unwrap();
+        [
+            Vector2Int::new(min_x, min_y),
+            Vector2Int::new(max_x, min_y),
+            Vector2Int::new(max_x, max_y),
+            Vector2Int::new(min_x, max_y),
+        ]
+    }
+
+    fn intersects(&self, other: &dyn Room, border: Option<u32>) -> bool {
+        let b = border.unwrap_or(0) as i32;
+
+        let other_tiles = other.to_tiles();
+
+        self.footprint().any(|tile| {
+            (tile.y - b..=tile.y + b).any(|y| {
+                (tile.x - b..=tile.x + b).any(|x| other_tiles.contains(&Vector2Int::new(x, y)))
+            })
+        })
+    }
+
+    fn shift(&mut self, offset: Vector2Int) {
+        let shift = |set: &BTreeSet<Vector2Int>| set.iter().map(|v| *v + offset).collect();
+        self.floor = shift(&self.floor);
+        self.walls = shift(&self.walls);
+        for v in self.entrances.iter_mut() {
+            *v += offset;
+        }
+        self.features = self
+            .features
+            .iter()
+            .map(|(v, kind)| (*v + offset, *kind))
+            .collect();
+        for (v, _) in self.spawns.iter_mut() {
+            *v += offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn prefab(chance: f64, rows: &[&str]) -> Prefab {
+        Prefab {
+            chance,
+            role: RoomRole::Common,
+            rows: rows.iter().map(|r| r.to_string()).collect(),
+            rotate: false,
+            mirror: false,
+            gold: 5,
+            health: 5,
+        }
+    }
+
+    #[test]
+    fn picks_prefabs_by_their_chance() {
+        let prefabs = [prefab(0.3, &["."]), prefab(0.3, &[".."])];
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut counts = [0; 3];
+        for _ in 0..10000 {
+            let picked = pick_prefab(&prefabs, &mut rng).map(|p| p.to_tiles().len());
+            counts[picked.unwrap_or(0)] += 1;
+        }
+        // none, the first and the second
+        assert!((3700..4300).contains(&counts[0]), "{:?}", counts);
+        assert!((2700..3300).contains(&counts[1]), "{:?}", counts);
+        assert!((2700..3300).contains(&counts[2]), "{:?}", counts);
+    }
+
+    #[test]
+    fn markers_take_their_value_from_the_prefab() {
+        let mut template = prefab(1., &["$!"]);
+        template.gold = 12;
+        template.health = 3;
+        let room = PrefabRoom::new(&template, 0, false).unwrap();
+        assert!(room
+            .spawns()
+            .contains(&(Vector2Int::new(0, 0), PrefabSpawn::Gold(12))));
+        assert!(room
+            .spawns()
+            .contains(&(Vector2Int::new(1, 0), PrefabSpawn::Health(3))));
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use rand::{thread_rng, Rng};
+use rand::{rngs::StdRng, Rng};
 
 use crate::vectors::Vector2Int;
 
@@ -30,8 +30,7 @@ impl Room for SquareRoom {
         ]
     }
 
-    fn random_point(&self) -> Vector2Int {
-        let mut rng = thread_rng();
+    fn random_point(&self, rng: &mut StdRng) -> Vector2Int {
         let x = rng.gen_range(self.a.x + 1..self.b.x);
         let y = rng.gen_range(self.a.y + 1..self.b.y);
         Vector2Int::new(x, y)
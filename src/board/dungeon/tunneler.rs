@@ -1,16 +1,32 @@
-use rand::{distributions::WeightedIndex, prelude::*};
+use std::collections::HashSet;
 
-use crate::vectors::Vector2Int;
+use rand::{distributions::WeightedIndex, prelude::*, rngs::StdRng};
+use serde::Deserialize;
+
+use crate::vectors::{find_path, Grid, Vector2Int, ORTHO_DIRECTIONS};
 
 pub trait Tunneler {
-    fn connect(&self, a: Vector2Int, b: Vector2Int) -> Vec<Vector2Int>;
+    // `rooms` are the tiles of the other rooms around, which a tunneler may try to avoid
+    fn connect(
+        &self,
+        a: Vector2Int,
+        b: Vector2Int,
+        rooms: &HashSet<Vector2Int>,
+        rng: &mut StdRng,
+    ) -> Vec<Vector2Int>;
 }
 
 pub struct LShapeTunneler;
 impl Tunneler for LShapeTunneler {
     // connects two points by forming an L shaped connection
     // initial direction (hor / ver) is the one whith the biggest coordinate difference
-    fn connect(&self, a: Vector2Int, b: Vector2Int) -> Vec<Vector2Int> {
+    fn connect(
+        &self,
+        a: Vector2Int,
+        b: Vector2Int,
+        _rooms: &HashSet<Vector2Int>,
+        _rng: &mut StdRng,
+    ) -> Vec<Vector2Int> {
         let d = b - a;
         let (hor_y, ver_x) = match d.x > d.y {
             true => (a.y, b.x),
@@ -32,27 +48,136 @@ impl Tunneler for RandomTunneler {
     // connects two points by taking a random direction (hor / ver) towards the target
     // choice chance is determined by a current coordinate difference
     // (it is most likely to pick a dir with the biggest diff)
-    fn connect(&self, a: Vector2Int, b: Vector2Int) -> Vec<Vector2Int> {
+    fn connect(
+        &self,
+        a: Vector2Int,
+        b: Vector2Int,
+        _rooms: &HashSet<Vector2Int>,
+        rng: &mut StdRng,
+    ) -> Vec<Vector2Int> {
         let mut cur = a;
         let mut path = Vec::new();
-        let mut rng = thread_rng();
 
         while cur != b {
             path.push(cur);
-            // 0 is horizontal, 1 is vertical
-            let dirs = [b.x - cur.x, b.y - cur.y];
-            // build weights
-            let dist = WeightedIndex::new(dirs.iter().map(|d| d.abs())).unwrap();
-            // pick a dir idx (0 or 1)
-            let dir_idx = dist.sample(&mut rng);
-            // create a normalized step vector in a single direction
-            let dv = match dir_idx {
-                0 => Vector2Int::new(dirs[0] / dirs[0].abs(), 0),
-                1 => Vector2Int::new(0, dirs[1] / dirs[1].abs()),
-                _ => panic!(),
+            cur += step_towards(cur, b, rng);
+        }
+        path
+    }
+}
+
+// a single step of the random tunneler, `cur` must not equal `target`
+fn step_towards(cur: Vector2Int, target: Vector2Int, rng: &mut StdRng) -> Vector2Int {
+    // 0 is horizontal, 1 is vertical
+    let dirs = [target.x - cur.x, target.y - cur.y];
+    // build weights
+    let dist = WeightedIndex::new(dirs.iter().map(|d| d.abs())).unwrap();
+    // pick a dir idx (0 or 1)
+    let dir_idx = dist.sample(rng);
+    // create a normalized step vector in a single direction
+    match dir_idx {
+        0 => Vector2Int::new(dirs[0] / dirs[0].abs(), 0),
+        1 => Vector2Int::new(0, dirs[1] / dirs[1].abs()),
+        _ => panic!(),
+    }
+}
+
+/// routes the tunnel around other rooms with a cheapest path search
+#[derive(Clone, Deserialize)]
+pub struct AStarTunneler {
+    // price of digging through another room, compared to 1 for solid rock
+    pub room_cost: u32,
+    // how far the tunnel may stray outside of the box spanned by its ends
+    pub margin: i32,
+}
+impl Tunneler for AStarTunneler {
+    fn connect(
+        &self,
+        a: Vector2Int,
+        b: Vector2Int,
+        rooms: &HashSet<Vector2Int>,
+        rng: &mut StdRng,
+    ) -> Vec<Vector2Int> {
+        let margin = Vector2Int::new(self.margin, self.margin);
+        let min = Vector2Int::new(a.x.min(b.x), a.y.min(b.y)) - margin;
+        let max = Vector2Int::new(a.x.max(b.x), a.y.max(b.y)) + margin;
+
+        // rock next to a room costs a bit more, so tunnels don't scrape along room edges
+        let mut costs = Grid::new(min, max, 1);
+        for v in rooms.iter() {
+            for n in ORTHO_DIRECTIONS.iter().map(|d| *v + *d) {
+                if costs.contains(n) && costs[n] == 1 {
+                    costs[n] = 3;
+                }
+            }
+        }
+        for v in rooms.iter() {
+            if costs.contains(*v) {
+                costs[*v] = self.room_cost.max(1);
+            }
+        }
+
+        match find_path(a, b, &costs, |c| Some(*c), &HashSet::new()) {
+            Some(path) => [a].into_iter().chain(path).collect(),
+            None => LShapeTunneler.connect(a, b, rooms, rng),
+        }
+    }
+}
+
+/// heads for the target like `RandomTunneler`, but stumbles in a random direction now and then
+#[derive(Clone, Deserialize)]
+pub struct DrunkardTunneler {
+    // chance of every step going in a random direction, capped at 0.9 so the walk still arrives
+    pub wiggle: f64,
+}
+impl Tunneler for DrunkardTunneler {
+    fn connect(
+        &self,
+        a: Vector2Int,
+        b: Vector2Int,
+        _rooms: &HashSet<Vector2Int>,
+        rng: &mut StdRng,
+    ) -> Vec<Vector2Int> {
+        let wiggle = self.wiggle.clamp(0., 0.9);
+        let mut cur = a;
+        let mut path = vec![a];
+        while cur != b {
+            cur += match rng.gen_bool(wiggle) {
+                true => *ORTHO_DIRECTIONS.choose(rng).unwrap(),
+                false => step_towards(cur, b, rng),
             };
-            cur += dv;
+            path.push(cur);
         }
         path
     }
 }
+
+/// widens the tunnels of another tunneler into a square brush of `width` tiles
+pub struct WideTunneler {
+    pub inner: Box<dyn Tunneler>,
+    pub width: u32,
+}
+impl Tunneler for WideTunneler {
+    fn connect(
+        &self,
+        a: Vector2Int,
+        b: Vector2Int,
+        rooms: &HashSet<Vector2Int>,
+        rng: &mut StdRng,
+    ) -> Vec<Vector2Int> {
+        let path = self.inner.connect(a, b, rooms, rng);
+        let width = self.width.max(1) as i32;
+        // keep the brush centred on the original path
+        let offsets = (-(width - 1) / 2..=width / 2).collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        path.into_iter()
+            .flat_map(|v| {
+                offsets
+                    .iter()
+                    .flat_map(|y| offsets.iter().map(|x| v + Vector2Int::new(*x, *y)))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|v| seen.insert(*v))
+            .collect()
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+
+use crate::vectors::{Grid, Vector2Int};
+
+use super::components::{mark_border_walls, TileKind, WallMaterial};
+
+/// a hand-authored level read from a character grid
+///
+/// glyphs:
+/// - `#` wall, walls on the edge of the map can't be dug
+/// - `.` floor
+/// - `@` player start
+/// - `>` stairs down
+/// - `<` stairs up
+/// - `n` npc
+/// - `$` gold
+/// - `!` health drop
+/// - `~` water
+/// - `%` lava
+/// - `_` chasm
+/// - `+` door
+/// - `=` locked door
+/// - `*` secret door
+/// - `k` key
+///
+/// any other character (including spaces) is left empty.
+/// the first line of the file is the top row of the map
+#[derive(Asset, TypePath, Default)]
+pub struct TextMap {
+    pub floor: HashSet<Vector2Int>,
+    pub walls: HashSet<Vector2Int>,
+    pub player_start: Option<Vector2Int>,
+    pub stairs_down: Option<Vector2Int>,
+    pub stairs_up: Option<Vector2Int>,
+    pub npcs: Vec<Vector2Int>,
+    pub coins: Vec<Vector2Int>,
+    pub health: Vec<Vector2Int>,
+    pub keys: Vec<Vector2Int>,
+    // floor tiles that are something other than plain floor
+    pub features: HashMap<Vector2Int, TileKind>,
+}
+
+impl TextMap {
+    pub fn parse(text: &str) -> TextMap {
+        let mut map = TextMap::default();
+        let lines = text.lines().collect::<Vec<_>>();
+        let height = lines.len() as i32;
+
+        for (row, line) in lines.iter().enumerate() {
+            // the board's y axis points up, so flip the rows
+            let y = height - 1 - row as i32;
+            for (x, c) in line.chars().enumerate() {
+                let v = Vector2Int::new(x as i32, y);
+                match c {
+                    '#' => {
+                        map.walls.insert(v);
+                        continue;
+                    }
+                    '.' => (),
+                    '@' => map.player_start = Some(v),
+                    '>' => map.stairs_down = Some(v),
+                    '<' => map.stairs_up = Some(v),
+                    'n' => map.npcs.push(v),
+                    '$' => map.coins.push(v),
+                    '!' => map.health.push(v),
+                    'k' => map.keys.push(v),
+                    _ => match TileKind::from_glyph(c) {
+                        Some(kind) => {
+                            map.features.insert(v, kind);
+                        }
+                        None => continue,
+                    },
+                }
+                map.floor.insert(v);
+            }
+        }
+        map
+    }
+
+    pub fn to_grid(&self) -> Grid<Option<TileKind>> {
+        let mut grid = Grid::covering(self.floor.union(&self.walls).copied(), None);
+        for v in self.floor.iter() {
+            let kind = self.features.get(v).copied().unwrap_or_default();
+            grid.set(*v, Some(kind));
+        }
+        for v in self.walls.iter() {
+            grid.set(
+                *v,
+                Some(TileKind::Wall {
+                    material: WallMaterial::Rock,
+                }),
+            );
+        }
+        mark_border_walls(&mut grid);
+        grid
+    }
+
+    /// sorted plain floor tiles, with the player start (if any) moved to the front
+    pub fn get_valid_spots(&self) -> Vec<Vector2Int> {
+        let mut spots = self
+            .floor
+            .iter()
+            .filter(|v| !self.features.contains_key(v))
+            .copied()
+            .collect::<Vec<_>>();
+        spots.sort();
+        if let Some(start) = self.player_start {
+            spots.retain(|v| *v != start);
+            spots.insert(0, start);
+        }
+        spots
+    }
+}
+
+#[derive(Default)]
+pub struct TextMapLoader;
+
+impl AssetLoader for TextMapLoader {
+    type Asset = TextMap;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut text = String::new();
+            reader.read_to_string(&mut text).await?;
+            Ok(TextMap::parse(&text))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+}
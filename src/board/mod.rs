@@ -1,18 +1,57 @@
 use bevy::prelude::*;
-use std::collections::HashMap;
+use rand::rngs::StdRng;
+use std::collections::{HashMap, HashSet};
 
-use crate::{states::MainState, vectors::Vector2Int};
+use crate::{
+    pieces::SavedPiece,
+    states::MainState,
+    vectors::{Grid, Vector2Int},
+};
 
 pub mod components;
 pub mod dungeon;
+pub mod map_loader;
+pub mod recipe;
 pub mod systems;
+pub mod theme;
 
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CurrentBoard>()
-            .add_systems(OnEnter(MainState::Game), systems::spawn_map)
+            .init_resource::<KeySpots>()
+            .init_resource::<PrefabSpawns>()
+            .init_resource::<LevelRooms>()
+            .init_resource::<RoomKinds>()
+            .init_resource::<DungeonDepth>()
+            .init_resource::<LevelCache>()
+            .init_resource::<LevelStairs>()
+            .init_resource::<LevelMaps>()
+            .init_resource::<theme::LevelThemes>()
+            .init_asset::<map_loader::TextMap>()
+            .init_asset_loader::<map_loader::TextMapLoader>()
+            .init_asset::<recipe::DungeonRecipes>()
+            .init_asset_loader::<recipe::DungeonRecipesLoader>()
+            .init_asset::<theme::Themes>()
+            .init_asset_loader::<theme::ThemesLoader>()
+            .add_systems(
+                Startup,
+                (
+                    systems::load_level_maps,
+                    systems::load_recipes,
+                    systems::load_themes,
+                ),
+            )
+            .add_systems(
+                OnEnter(MainState::Game),
+                (
+                    systems::seed_dungeon,
+                    systems::reset_depth,
+                    systems::spawn_map,
+                )
+                    .chain(),
+            )
             .add_systems(
                 Update,
                 systems::update_tile_visibility.run_if(in_state(MainState::Game)),
@@ -23,8 +62,96 @@ impl Plugin for BoardPlugin {
 
 #[derive(Default, Resource)]
 pub struct CurrentBoard {
-    pub tiles: HashMap<Vector2Int, Entity>,
+    // tile entities, `None` where the board has no tile
+    pub tiles: Grid<Option<Entity>>,
+}
+
+impl CurrentBoard {
+    pub fn get_tile(&self, v: Vector2Int) -> Option<Entity> {
+        self.tiles.get(v).copied().flatten()
+    }
 }
 
 #[derive(Resource)]
 pub struct ValidSpots(pub Vec<Vector2Int>);
+
+/// the kind of room each floor tile belongs to, empty unless the floor was generated
+#[derive(Default, Resource)]
+pub struct RoomKinds(pub HashMap<Vector2Int, dungeon::room::RoomKind>);
+
+/// where the keys for the current level's locked doors are placed
+#[derive(Default, Resource)]
+pub struct KeySpots(pub Vec<Vector2Int>);
+
+/// the rooms of the current level, empty unless the level was generated
+#[derive(Default, Resource)]
+pub struct LevelRooms(pub Vec<dungeon::RoomInfo>);
+
+impl LevelRooms {
+    /// the first room with the given role
+    pub fn with_role(&self, role: dungeon::room::RoomRole) -> Option<&dungeon::RoomInfo> {
+        self.0.iter().find(|r| r.role == role)
+    }
+}
+
+/// spawn markers of the prefabs placed on the current level
+#[derive(Default, Resource)]
+pub struct PrefabSpawns(pub Vec<(Vector2Int, dungeon::room::PrefabSpawn)>);
+
+/// seed the current run was started from
+#[derive(Resource)]
+pub struct DungeonSeed(pub u64);
+
+/// rng used for map generation and spawning, seeded from `DungeonSeed`
+#[derive(Resource)]
+pub struct DungeonRng(pub StdRng);
+
+/// rng used for turn to turn rolls (npc moves, traps, damage), also seeded from `DungeonSeed`
+///
+/// kept apart from `DungeonRng`, so that playing differently can't change the floors generated later
+#[derive(Resource)]
+pub struct GameRng(pub StdRng);
+
+/// how far down the player is, the first floor is depth 0
+#[derive(Default, Resource)]
+pub struct DungeonDepth {
+    pub current: u32,
+    // true when the current floor was entered by going up the stairs
+    pub ascended: bool,
+}
+
+/// stair positions on the current floor, there is no way up from depth 0
+#[derive(Clone, Copy, Default, Resource)]
+pub struct LevelStairs {
+    pub up: Option<Vector2Int>,
+    pub down: Option<Vector2Int>,
+}
+
+/// state of every floor the player has left, by depth
+#[derive(Default, Resource)]
+pub struct LevelCache(pub HashMap<u32, SavedLevel>);
+
+pub struct SavedLevel {
+    pub tiles: Grid<Option<components::TileKind>>,
+    pub seen: HashSet<Vector2Int>,
+    pub open_doors: HashSet<Vector2Int>,
+    pub valid_spots: Vec<Vector2Int>,
+    pub stairs: LevelStairs,
+    pub rooms: Vec<dungeon::RoomInfo>,
+    pub themes: theme::LevelThemes,
+    pub pieces: Vec<SavedPiece>,
+}
+
+/// hand-authored maps that replace the generated dungeon on specific levels
+#[derive(Default, Resource)]
+pub struct LevelMaps(pub HashMap<u32, Handle<map_loader::TextMap>>);
+
+/// piece placements taken from a hand-authored map
+///
+/// only present while the current level was loaded from a `TextMap`
+#[derive(Resource)]
+pub struct FixedSpawns {
+    pub npcs: Vec<Vector2Int>,
+    pub coins: Vec<Vector2Int>,
+    pub health: Vec<Vector2Int>,
+}
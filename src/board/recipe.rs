@@ -0,0 +1,443 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use rand::rngs::StdRng;
+use serde::Deserialize;
+
+use super::components::TileKind;
+use super::dungeon::{room, tunneler, Area, Dungeon};
+use crate::pieces::spawn_table::{choose_weighted, in_depth};
+
+pub const RECIPES_PATH: &str = "dungeon.recipes.ron";
+
+/// every level style a generated floor can be built from
+#[derive(Asset, TypePath, Deserialize)]
+pub struct DungeonRecipes {
+    pub recipes: Vec<DungeonRecipe>,
+}
+
+/// describes how to generate one style of floor
+#[derive(Deserialize)]
+pub struct DungeonRecipe {
+    pub name: String,
+    // relative chance of being picked among the recipes that fit the depth
+    pub weight: u32,
+    #[serde(default)]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    // areas are laid out in a grid with this many rows
+    pub rows: usize,
+    // empty tiles left between neighbouring areas
+    #[serde(default)]
+    pub area_spacing: i32,
+    pub areas: Vec<AreaRecipe>,
+    pub doors: DoorRecipe,
+    #[serde(default)]
+    pub pools: Vec<PoolRecipe>,
+    // name of the level theme, a random one fitting the depth is picked if not set
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AreaRecipe {
+    pub tunneler: TunnelerRecipe,
+    pub generator: GeneratorRecipe,
+    // tunnels wider than a single tile are dug with a square brush
+    #[serde(default)]
+    pub corridor_width: u32,
+    // gives the area a look of its own instead of the level theme
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub enum TunnelerRecipe {
+    LShape,
+    Random,
+    AStar(tunneler::AStarTunneler),
+    Drunkard(tunneler::DrunkardTunneler),
+}
+
+#[derive(Clone, Deserialize)]
+pub enum GeneratorRecipe {
+    Bubble(room::BubbleGenerator),
+    Bsp(room::BspGenerator),
+    Cave(room::CaveGenerator),
+    Maze(room::MazeGenerator),
+}
+
+#[derive(Deserialize)]
+pub struct DoorRecipe {
+    pub chance: f64,
+    pub locked_chance: f64,
+    // added to the locked chance on every floor
+    #[serde(default)]
+    pub locked_chance_per_depth: f64,
+    #[serde(default)]
+    pub max_locked_chance: Option<f64>,
+    // chance of an unlocked door being hidden, only doors on loops can be
+    #[serde(default)]
+    pub secret_chance: f64,
+}
+
+#[derive(Deserialize)]
+pub struct PoolRecipe {
+    pub kind: TileKind,
+    pub count: u32,
+    // extra pools on every floor
+    #[serde(default)]
+    pub per_depth: u32,
+}
+
+impl DungeonRecipes {
+    /// picks a weighted random recipe that is allowed at the given depth
+    pub fn choose(&self, depth: u32, rng: &mut StdRng) -> Option<&DungeonRecipe> {
+        let candidates = self
+            .recipes
+            .iter()
+            .filter(|r| in_depth(r.min_depth, r.max_depth, depth))
+            .map(|r| (r, r.weight));
+        choose_weighted(candidates, rng)
+    }
+
+    /// rejects recipes that can't be built, so a broken file fails to load
+    /// instead of panicking once a floor is generated from it
+    pub fn validate(&self) -> Result<(), String> {
+        for recipe in self.recipes.iter() {
+            recipe
+                .validate()
+                .map_err(|e| format!("recipe {}: {}", recipe.name, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl DungeonRecipe {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.areas.is_empty() {
+            return Err("no areas".to_string());
+        }
+        // every row needs at least one area
+        if self.rows == 0 || self.rows > self.areas.len() {
+            return Err(format!("{} rows for {} areas", self.rows, self.areas.len()));
+        }
+        for area in self.areas.iter() {
+            area.generator.build().validate()?;
+        }
+        self.doors.validate()
+    }
+
+    /// generates the full dungeon, including doors and pools
+    pub fn build(&self, depth: u32, rng: &mut StdRng) -> Dungeon {
+        let mut dungeon = Dungeon::new(self.rows.max(1));
+        dungeon.area_spacing = self.area_spacing;
+        for area in self.areas.iter() {
+            let tunneler = match area.corridor_width {
+                0 | 1 => area.tunneler.build(),
+                width => Box::new(tunneler::WideTunneler {
+                    inner: area.tunneler.build(),
+                    width,
+                }),
+            };
+            dungeon.add_area(Area::new(tunneler, area.generator.build()));
+        }
+        dungeon.generate(rng);
+
+        let locked_chance = (self.doors.locked_chance
+            + self.doors.locked_chance_per_depth * depth as f64)
+            .min(self.doors.max_locked_chance.unwrap_or(1.0))
+            .clamp(0., 1.);
+        dungeon.add_doors(self.doors.chance, locked_chance, rng);
+        dungeon.add_secret_doors(self.doors.secret_chance, rng);
+        for pool in self.pools.iter() {
+            dungeon.add_pools(pool.kind, pool.count + pool.per_depth * depth, rng);
+        }
+        dungeon
+    }
+}
+
+impl DoorRecipe {
+    fn validate(&self) -> Result<(), String> {
+        room::check_chance("door chance", self.chance)?;
+        room::check_chance("door locked_chance", self.locked_chance)?;
+        if let Some(max) = self.max_locked_chance {
+            room::check_chance("door max_locked_chance", max)?;
+        }
+        room::check_chance("door secret_chance", self.secret_chance)?;
+        // the locked chance is clamped on every floor, so any finite growth is fine
+        if !self.locked_chance_per_depth.is_finite() {
+            return Err("door locked_chance_per_depth has to be a number".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for DungeonRecipe {
+    /// the mixed layout used when no recipe asset is available
+    fn default() -> Self {
+        let tunnelers = [TunnelerRecipe::LShape, TunnelerRecipe::Random];
+        let generators = [
+            GeneratorRecipe::Bubble(room::BubbleGenerator {
+                room_count: (3, 5),
+                room_size: (4, 8),
+                room_padding: Some(2),
+                extra_connection_chance: 0.25,
+                prefabs: Vec::new(),
+            }),
+            GeneratorRecipe::Bsp(room::BspGenerator {
+                size: (32, 24),
+                min_leaf_size: 8,
+                max_depth: 4,
+                min_room_size: 4,
+                room_padding: 1,
+                prefabs: Vec::new(),
+            }),
+            GeneratorRecipe::Cave(room::CaveGenerator {
+                size: (32, 24),
+                floor_chance: 0.55,
+                smoothing_steps: 4,
+                min_cavern_size: 12,
+                prefabs: Vec::new(),
+            }),
+        ];
+        let areas = generators
+            .into_iter()
+            .flat_map(|generator| {
+                tunnelers.clone().map(|tunneler| AreaRecipe {
+                    tunneler,
+                    generator: generator.clone(),
+                    corridor_width: 1,
+                    theme: None,
+                })
+            })
+            .collect();
+        DungeonRecipe {
+            name: "Default".to_string(),
+            weight: 1,
+            min_depth: 0,
+            max_depth: None,
+            rows: 2,
+            area_spacing: 0,
+            areas,
+            doors: DoorRecipe {
+                chance: 0.5,
+                locked_chance: 0.1,
+                locked_chance_per_depth: 0.05,
+                max_locked_chance: Some(0.5),
+                secret_chance: 0.2,
+            },
+            pools: vec![
+                PoolRecipe {
+                    kind: TileKind::Water,
+                    count: 4,
+                    per_depth: 0,
+                },
+                PoolRecipe {
+                    kind: TileKind::Lava,
+                    count: 2,
+                    per_depth: 1,
+                },
+                PoolRecipe {
+                    kind: TileKind::Chasm,
+                    count: 1,
+                    per_depth: 0,
+                },
+            ],
+            theme: None,
+        }
+    }
+}
+
+impl TunnelerRecipe {
+    fn build(&self) -> Box<dyn tunneler::Tunneler> {
+        match self {
+            TunnelerRecipe::LShape => Box::new(tunneler::LShapeTunneler),
+            TunnelerRecipe::Random => Box::new(tunneler::RandomTunneler),
+            TunnelerRecipe::AStar(t) => Box::new(t.clone()),
+            TunnelerRecipe::Drunkard(t) => Box::new(t.clone()),
+        }
+    }
+}
+
+impl GeneratorRecipe {
+    fn build(&self) -> Box<dyn room::RoomGenerator> {
+        match self {
+            GeneratorRecipe::Bubble(g) => Box::new(g.clone()),
+            GeneratorRecipe::Bsp(g) => Box::new(g.clone()),
+            GeneratorRecipe::Cave(g) => Box::new(g.clone()),
+            GeneratorRecipe::Maze(g) => Box::new(g.clone()),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct DungeonRecipesHandle(pub Handle<DungeonRecipes>);
+
+#[derive(Default)]
+pub struct DungeonRecipesLoader;
+
+impl AssetLoader for DungeonRecipesLoader {
+    type Asset = DungeonRecipes;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let recipes = ron::de::from_bytes::<DungeonRecipes>(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            recipes
+                .validate()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            Ok(recipes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["recipes.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn shipped() -> DungeonRecipes {
+        ron::de::from_str(include_str!("../../assets/dungeon.recipes.ron")).unwrap()
+    }
+
+    #[test]
+    fn shipped_recipes_are_valid() {
+        assert_eq!(shipped().validate(), Ok(()));
+        assert_eq!(DungeonRecipe::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn shipped_recipes_build() {
+        for recipe in shipped().recipes.iter() {
+            for seed in 0..5 {
+                let dungeon = recipe.build(recipe.min_depth, &mut StdRng::seed_from_u64(seed));
+                assert!(dungeon.is_connected(), "{} seed {}", recipe.name, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn recipe_without_areas_fails_validation() {
+        let mut recipe = DungeonRecipe::default();
+        recipe.areas.clear();
+        assert!(recipe.validate().is_err());
+    }
+
+    #[test]
+    fn recipe_with_empty_rows_fails_validation() {
+        let mut recipe = DungeonRecipe::default();
+        recipe.rows = recipe.areas.len() + 1;
+        assert!(recipe.validate().is_err());
+    }
+
+    #[test]
+    fn bsp_without_room_for_a_room_fails_validation() {
+        let mut recipe = DungeonRecipe::default();
+        recipe.areas[0].generator = GeneratorRecipe::Bsp(room::BspGenerator {
+            size: (5, 24),
+            min_leaf_size: 8,
+            max_depth: 4,
+            min_room_size: 4,
+            room_padding: 1,
+            prefabs: Vec::new(),
+        });
+        assert!(recipe.validate().is_err());
+    }
+
+    #[test]
+    fn empty_maze_fails_validation() {
+        let mut recipe = DungeonRecipe::default();
+        recipe.areas[0].generator = GeneratorRecipe::Maze(room::MazeGenerator {
+            size: (0, 6),
+            corridor_width: 1,
+            loop_chance: 0.,
+            braid: 0.,
+        });
+        assert!(recipe.validate().is_err());
+    }
+
+    #[test]
+    fn cave_with_a_bad_floor_chance_fails_validation() {
+        let mut recipe = DungeonRecipe::default();
+        let cave = |floor_chance| {
+            GeneratorRecipe::Cave(room::CaveGenerator {
+                size: (20, 20),
+                floor_chance,
+                smoothing_steps: 4,
+                min_cavern_size: 5,
+                prefabs: Vec::new(),
+            })
+        };
+        recipe.areas[0].generator = cave(0.45);
+        assert_eq!(recipe.validate(), Ok(()));
+        for floor_chance in [1.2, -0.1, f64::NAN] {
+            recipe.areas[0].generator = cave(floor_chance);
+            assert!(recipe.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn bad_door_chances_fail_validation() {
+        let mut recipe = DungeonRecipe::default();
+        recipe.doors.chance = 1.5;
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = DungeonRecipe::default();
+        recipe.doors.locked_chance = -0.1;
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = DungeonRecipe::default();
+        recipe.doors.max_locked_chance = Some(2.);
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = DungeonRecipe::default();
+        recipe.doors.locked_chance_per_depth = f64::INFINITY;
+        assert!(recipe.validate().is_err());
+
+        let mut recipe = DungeonRecipe::default();
+        recipe.doors.secret_chance = 1.1;
+        assert!(recipe.validate().is_err());
+    }
+
+    #[test]
+    fn locked_chance_stays_a_chance_at_any_depth() {
+        for per_depth in [-0.5, 0.5] {
+            let mut recipe = DungeonRecipe::default();
+            recipe.doors.locked_chance_per_depth = per_depth;
+            recipe.doors.max_locked_chance = None;
+            assert_eq!(recipe.validate(), Ok(()));
+            recipe.build(10, &mut StdRng::seed_from_u64(1));
+        }
+    }
+
+    #[test]
+    fn bubble_with_a_bad_extra_connection_chance_fails_validation() {
+        let mut recipe = DungeonRecipe::default();
+        recipe.areas[0].generator = GeneratorRecipe::Bubble(room::BubbleGenerator {
+            room_count: (3, 5),
+            room_size: (4, 8),
+            room_padding: Some(2),
+            extra_connection_chance: 1.25,
+            prefabs: Vec::new(),
+        });
+        assert!(recipe.validate().is_err());
+    }
+}
@@ -1,85 +1,465 @@
-use crate::pieces::components::Occupier;
+use crate::actions::{NextLevelEvent, PreviousLevelEvent};
+use crate::messages::{MessageLog, Severity};
+use crate::options::LaunchOptions;
+use crate::pieces::{components::Occupier, SavedPieces};
 use crate::player::Player;
-use crate::vectors::{line_of_sight, Vector2Int};
+use crate::vectors::{line_of_sight, Grid, Vector2Int};
 
-use super::components::{Position, Tile, VisionBlocker, Wall};
-use super::dungeon::{room, tunneler, Area, Dungeon};
-use super::{CurrentBoard, ValidSpots};
+use super::components::{Door, Position, SecretDoor, Tile, TileKind, VisionBlocker, Wall};
+use super::dungeon::{connectivity, room::RoomRole, Dungeon, RoomInfo};
+use super::map_loader::TextMap;
+use super::recipe::{DungeonRecipe, DungeonRecipes, DungeonRecipesHandle};
+use super::theme::{LevelThemes, Themes, ThemesHandle};
+use super::{
+    CurrentBoard, DungeonDepth, DungeonRng, DungeonSeed, FixedSpawns, GameRng, KeySpots,
+    LevelCache, LevelMaps, LevelRooms, LevelStairs, PrefabSpawns, RoomKinds, SavedLevel,
+    ValidSpots,
+};
 use bevy::prelude::*;
-use std::collections::{HashMap, HashSet};
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use std::collections::HashSet;
 
 pub const VISIBILITY_RANGE: i32 = 10;
 
-pub fn spawn_map(mut commands: Commands, mut current: ResMut<CurrentBoard>) {
-    let mut dungeon = Dungeon::new(2);
-    for idx in 0..4 {
-        let tun = match idx % 2 {
-            0 => Box::new(tunneler::LShapeTunneler) as Box<dyn tunneler::Tunneler>,
-            _ => Box::new(tunneler::RandomTunneler) as Box<dyn tunneler::Tunneler>,
+pub fn seed_dungeon(mut commands: Commands, options: Res<LaunchOptions>) {
+    // use the seed from the command line if one was given, otherwise roll a new one
+    let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+    info!("Dungeon seed: {}", seed);
+    commands.insert_resource(DungeonSeed(seed));
+    commands.insert_resource(DungeonRng(StdRng::seed_from_u64(seed)));
+    // a different stream, so the two rngs don't mirror each other
+    commands.insert_resource(GameRng(StdRng::seed_from_u64(seed.wrapping_add(1))));
+}
+
+pub fn load_recipes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DungeonRecipesHandle(
+        asset_server.load(super::recipe::RECIPES_PATH),
+    ));
+}
+
+pub fn load_themes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(ThemesHandle(asset_server.load(super::theme::THEMES_PATH)));
+}
+
+pub fn load_level_maps(
+    options: Res<LaunchOptions>,
+    asset_server: Res<AssetServer>,
+    mut level_maps: ResMut<LevelMaps>,
+) {
+    for (level, path) in options.maps.iter() {
+        level_maps.0.insert(*level, asset_server.load(path.clone()));
+    }
+}
+
+pub fn reset_depth(mut depth: ResMut<DungeonDepth>, mut cache: ResMut<LevelCache>) {
+    *depth = DungeonDepth::default();
+    cache.0.clear();
+}
+
+/// run condition of the level setup, there is nothing to go up to from depth 0
+pub fn level_changed(
+    mut ev_next_level: EventReader<NextLevelEvent>,
+    mut ev_previous_level: EventReader<PreviousLevelEvent>,
+    depth: Res<DungeonDepth>,
+) -> bool {
+    let up = ev_previous_level.read().count() > 0;
+    let down = ev_next_level.read().count() > 0;
+    down || (up && depth.current > 0)
+}
+
+pub fn change_depth(
+    mut depth: ResMut<DungeonDepth>,
+    mut ev_next_level: EventReader<NextLevelEvent>,
+    mut ev_previous_level: EventReader<PreviousLevelEvent>,
+    mut log: ResMut<MessageLog>,
+) {
+    let up = ev_previous_level.read().count() > 0;
+    let down = ev_next_level.read().count() > 0;
+    if up && depth.current > 0 {
+        depth.current -= 1;
+        depth.ascended = true;
+        log.add(
+            Severity::Info,
+            format!("you climb up to depth {}", depth.current),
+        );
+    } else if down {
+        depth.current += 1;
+        depth.ascended = false;
+        log.add(
+            Severity::Info,
+            format!("you descend to depth {}", depth.current),
+        );
+    }
+}
+
+/// stores the floor that is about to be left in the level cache
+///
+/// pieces are added to the same entry by `pieces::save_pieces`
+#[allow(clippy::too_many_arguments)]
+pub fn save_level(
+    current: Res<CurrentBoard>,
+    tile_query: Query<(&Tile, &Position)>,
+    door_query: Query<(&Door, &Position)>,
+    depth: Res<DungeonDepth>,
+    valid_spots: Res<ValidSpots>,
+    stairs: Res<LevelStairs>,
+    rooms: Res<LevelRooms>,
+    themes: Res<LevelThemes>,
+    mut cache: ResMut<LevelCache>,
+) {
+    let (min, max) = current.tiles.bounds();
+    let mut tiles = Grid::new(min, max, None);
+    let mut seen = HashSet::new();
+    for (tile, position) in tile_query.iter() {
+        tiles.set(position.v, Some(tile.kind));
+        if tile.seen {
+            seen.insert(position.v);
+        }
+    }
+    let open_doors = door_query
+        .iter()
+        .filter(|(door, _)| door.open)
+        .map(|(_, position)| position.v)
+        .collect();
+
+    cache.0.insert(
+        depth.current,
+        SavedLevel {
+            tiles,
+            seen,
+            open_doors,
+            valid_spots: valid_spots.0.clone(),
+            stairs: *stairs,
+            rooms: rooms.0.clone(),
+            themes: themes.clone(),
+            pieces: Vec::new(),
+        },
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_map(
+    mut commands: Commands,
+    mut current: ResMut<CurrentBoard>,
+    mut rng: ResMut<DungeonRng>,
+    depth: Res<DungeonDepth>,
+    mut cache: ResMut<LevelCache>,
+    level_maps: Res<LevelMaps>,
+    text_maps: Res<Assets<TextMap>>,
+    recipes_handle: Res<DungeonRecipesHandle>,
+    recipes: Res<Assets<DungeonRecipes>>,
+    themes_handle: Res<ThemesHandle>,
+    themes: Res<Assets<Themes>>,
+) {
+    let text_map = level_maps
+        .0
+        .get(&depth.current)
+        .and_then(|handle| text_maps.get(handle));
+    if level_maps.0.contains_key(&depth.current) && text_map.is_none() {
+        warn!(
+            "Map for depth {} is not loaded, generating one",
+            depth.current
+        );
+    }
+
+    let (mut valid_spots, stairs) = if let Some(saved) = cache.0.remove(&depth.current) {
+        // the floor was visited before, bring it back the way it was left
+        spawn_tiles(
+            &mut commands,
+            &mut current,
+            &saved.tiles,
+            &saved.seen,
+            &saved.open_doors,
+        );
+        commands.remove_resource::<FixedSpawns>();
+        commands.insert_resource(KeySpots(Vec::new()));
+        commands.insert_resource(LevelRooms(saved.rooms));
+        commands.insert_resource(saved.themes);
+        commands.insert_resource(SavedPieces(saved.pieces));
+        (saved.valid_spots, saved.stairs)
+    } else if let Some(map) = text_map {
+        commands.remove_resource::<SavedPieces>();
+        commands.insert_resource(RoomKinds::default());
+        commands.insert_resource(LevelRooms::default());
+        commands.insert_resource(LevelThemes::default());
+        spawn_text_map(&mut commands, &mut current, &mut rng.0, map, depth.current)
+    } else {
+        commands.remove_resource::<SavedPieces>();
+        commands.remove_resource::<FixedSpawns>();
+        let recipe = recipes
+            .get(&recipes_handle.0)
+            .and_then(|r| r.choose(depth.current, &mut rng.0));
+        let default_recipe;
+        let recipe = match recipe {
+            Some(recipe) => recipe,
+            None => {
+                warn!(
+                    "No dungeon recipe for depth {}, using the default layout",
+                    depth.current
+                );
+                default_recipe = DungeonRecipe::default();
+                &default_recipe
+            }
         };
-        let gen = Box::new(room::BubbleGenerator {
-            room_count: (3, 5),
-            room_size: (4, 8),
-            room_padding: Some(2),
-            extra_connection_chance: 0.25,
-        }) as Box<dyn room::RoomGenerator>;
-        dungeon.add_area(Area::new(tun, gen))
+        let themes = themes.get(&themes_handle.0);
+        if themes.is_none() {
+            warn!("Themes are not loaded, the floor keeps the default look");
+        }
+        spawn_dungeon(
+            &mut commands,
+            &mut current,
+            &mut rng.0,
+            recipe,
+            themes,
+            depth.current,
+        )
+    };
+
+    // the player arrives on the stairs they took
+    let arrival = if depth.ascended {
+        stairs.down
+    } else {
+        stairs.up
+    };
+    if let Some(v) = arrival {
+        valid_spots.retain(|s| *s != v);
+        valid_spots.insert(0, v);
+    }
+
+    commands.insert_resource(ValidSpots(valid_spots));
+    commands.insert_resource(stairs);
+}
+
+fn spawn_text_map(
+    commands: &mut Commands,
+    current: &mut CurrentBoard,
+    rng: &mut StdRng,
+    map: &TextMap,
+    depth: u32,
+) -> (Vec<Vector2Int>, LevelStairs) {
+    let valid_spots = map.get_valid_spots();
+    if let Some(start) = valid_spots.first() {
+        let unreachable = connectivity::unreachable_tiles(*start, &map.floor);
+        if !unreachable.is_empty() {
+            warn!(
+                "Map for depth {} has {} tiles unreachable from the start",
+                depth,
+                unreachable.len()
+            );
+        }
     }
-    dungeon.generate();
+    spawn_tiles(
+        commands,
+        current,
+        &map.to_grid(),
+        &HashSet::new(),
+        &HashSet::new(),
+    );
+    commands.insert_resource(KeySpots(map.keys.clone()));
+    commands.insert_resource(FixedSpawns {
+        npcs: map.npcs.clone(),
+        coins: map.coins.clone(),
+        health: map.health.clone(),
+    });
+
+    let stairs = LevelStairs {
+        up: match depth {
+            0 => None,
+            _ => map.stairs_up.or(valid_spots.first().copied()),
+        },
+        down: map
+            .stairs_down
+            .or_else(|| random_stairs_spot(&valid_spots, rng)),
+    };
+    (valid_spots, stairs)
+}
+
+fn spawn_dungeon(
+    commands: &mut Commands,
+    current: &mut CurrentBoard,
+    rng: &mut StdRng,
+    recipe: &DungeonRecipe,
+    themes: Option<&Themes>,
+    depth: u32,
+) -> (Vec<Vector2Int>, LevelStairs) {
+    debug!("Generating depth {} from the {} recipe", depth, recipe.name);
+    let level = generate_level(recipe, depth, rng);
+    if !level.dungeon.is_connected() {
+        warn!("Generated dungeon is not fully connected");
+    }
+    // themed after the floor is generated, so the layout of a seed doesn't depend on the themes
+    let level_themes = themes
+        .map(|themes| LevelThemes::generate(&level.dungeon, recipe, themes, depth, rng))
+        .unwrap_or_default();
+
+    spawn_tiles(
+        commands,
+        current,
+        &level.dungeon.to_grid(),
+        &HashSet::new(),
+        &HashSet::new(),
+    );
+    commands.insert_resource(KeySpots(level.key_spots));
+    commands.insert_resource(PrefabSpawns(level.dungeon.get_prefab_spawns()));
+    commands.insert_resource(RoomKinds(level.dungeon.get_room_kinds()));
+    commands.insert_resource(LevelRooms(level.rooms));
+    commands.insert_resource(level_themes);
+    (level.valid_spots, level.stairs)
+}
+
+/// a generated floor before anything of it is spawned
+pub struct GeneratedLevel {
+    pub dungeon: Dungeon,
+    pub valid_spots: Vec<Vector2Int>,
+    pub key_spots: Vec<Vector2Int>,
+    pub rooms: Vec<RoomInfo>,
+    pub stairs: LevelStairs,
+}
 
+/// builds a floor from the recipe and places its keys and stairs
+///
+/// does not need a running app, so floors can be previewed outside of the game
+pub fn generate_level(recipe: &DungeonRecipe, depth: u32, rng: &mut StdRng) -> GeneratedLevel {
+    let dungeon = recipe.build(depth, rng);
     let valid_spots = dungeon.get_valid_spots();
+    let key_spots = dungeon.get_key_spots(rng);
+    let rooms = dungeon.get_rooms();
+
+    // the stairs down go into the exit room
+    let exit_spots = rooms
+        .iter()
+        .find(|r| r.role == RoomRole::Exit)
+        .map(|r| {
+            r.tiles
+                .iter()
+                .filter(|v| valid_spots.binary_search(v).is_ok())
+                .copied()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let stairs = LevelStairs {
+        up: match depth {
+            0 => None,
+            _ => valid_spots.first().copied(),
+        },
+        down: exit_spots
+            .choose(rng)
+            .copied()
+            .or_else(|| random_stairs_spot(&valid_spots, rng)),
+    };
+    GeneratedLevel {
+        dungeon,
+        valid_spots,
+        key_spots,
+        rooms,
+        stairs,
+    }
+}
+
+/// any valid spot but the player start
+fn random_stairs_spot(valid_spots: &[Vector2Int], rng: &mut StdRng) -> Option<Vector2Int> {
+    match valid_spots.len() {
+        0 => None,
+        1 => Some(valid_spots[0]),
+        n => Some(valid_spots[rng.gen_range(1..n)]),
+    }
+}
 
-    current.tiles = HashMap::new();
-    for v in dungeon.to_tiles() {
+fn spawn_tiles(
+    commands: &mut Commands,
+    current: &mut CurrentBoard,
+    tiles: &Grid<Option<TileKind>>,
+    seen: &HashSet<Vector2Int>,
+    open_doors: &HashSet<Vector2Int>,
+) {
+    let (min, max) = tiles.bounds();
+    current.tiles = Grid::new(min, max, None);
+    for (v, kind) in tiles.iter() {
+        let Some(kind) = *kind else {
+            continue;
+        };
         let tile = commands
             .spawn((
                 Position { v },
                 Tile {
                     visible: false,
-                    seen: false,
+                    seen: seen.contains(&v),
+                    kind,
                 },
             ))
             .id();
-        if dungeon.walls.contains(&v) {
-            commands.entity(tile).with_children(|parent| {
-                parent
-                    .spawn(Occupier)
-                    .insert(Wall)
-                    .insert(VisionBlocker)
-                    .insert(Position { v });
-            });
+        match kind {
+            TileKind::Wall { material } => {
+                commands.entity(tile).with_children(|parent| {
+                    parent
+                        .spawn(Occupier)
+                        .insert(Wall {
+                            durability: material.durability(),
+                        })
+                        .insert(VisionBlocker)
+                        .insert(Position { v });
+                });
+            }
+            TileKind::Door { locked } => {
+                let open = open_doors.contains(&v);
+                commands.entity(tile).with_children(|parent| {
+                    let mut door = parent.spawn((Door { open, locked }, Position { v }));
+                    if !open {
+                        door.insert((Occupier, VisionBlocker));
+                    }
+                });
+            }
+            TileKind::SecretDoor => {
+                commands.entity(tile).with_children(|parent| {
+                    parent.spawn((
+                        SecretDoor::default(),
+                        Position { v },
+                        Occupier,
+                        VisionBlocker,
+                    ));
+                });
+            }
+            _ => (),
         }
-        current.tiles.insert(v, tile);
+        current.tiles.set(v, Some(tile));
     }
-
-    commands.insert_resource(ValidSpots(valid_spots));
 }
 
 pub fn update_tile_visibility(
     player_query: Query<&Position, With<Player>>,
     mut tile_query: Query<(&mut Tile, &Position), Without<Player>>,
     blocker_query: Query<&Position, With<VisionBlocker>>,
+    current: Res<CurrentBoard>,
 ) {
     let Ok(player_position) = player_query.get_single() else {
         return;
     };
 
-    let blocker_positions: HashSet<Vector2Int> = blocker_query.iter().map(|b| b.v).collect();
+    let (min, max) = current.tiles.bounds();
+    let mut blockers = Grid::new(min, max, false);
+    for position in blocker_query.iter() {
+        blockers.set(position.v, true);
+    }
+    for (tile, position) in tile_query.iter() {
+        if tile.kind.blocks_vision() {
+            blockers.set(position.v, true);
+        }
+    }
 
     let area = player_position.v.circle_area(VISIBILITY_RANGE);
 
-    let visible_positions = line_of_sight(player_position.v, area, &blocker_positions);
+    let visible_positions = line_of_sight(player_position.v, area, &blockers);
 
     for (mut tile, position) in tile_query.iter_mut() {
         let within_range = position.v.distance(player_position.v) <= VISIBILITY_RANGE;
 
-        let in_sight = visible_positions.contains(&position.v);
+        let in_sight = visible_positions.get(position.v) == Some(&true);
 
-        if within_range && in_sight {
-            tile.visible = true;
-            tile.seen = true;
-        } else {
-            tile.visible = false;
+        // only touch the tiles that changed, so Changed<Tile> stays meaningful
+        let visible = within_range && in_sight;
+        if tile.visible != visible || (visible && !tile.seen) {
+            tile.visible = visible;
+            tile.seen |= visible;
         }
     }
 }
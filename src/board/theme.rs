@@ -0,0 +1,291 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use rand::{rngs::StdRng, Rng};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::components::TileKind;
+use super::dungeon::{room::check_chance, Dungeon};
+use super::recipe::DungeonRecipe;
+use crate::pieces::spawn_table::{choose_weighted, in_depth};
+use crate::vectors::Vector2Int;
+
+pub const THEMES_PATH: &str = "level.themes.ron";
+
+/// every look a level (or a single area of it) can be given
+#[derive(Asset, TypePath, Deserialize)]
+pub struct Themes {
+    pub themes: Vec<Theme>,
+}
+
+/// sprites, tints and decorations of a level style
+///
+/// themes are independent of the recipe the floor is generated from,
+/// spawn table entries can be limited to some themes by name
+#[derive(Clone, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    // relative chance of being picked for a level whose recipe doesn't name a theme
+    pub weight: u32,
+    #[serde(default)]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    // atlas indexes
+    pub floor_sprite: usize,
+    pub wall_sprite: usize,
+    // rgb the tile sprites are multiplied with
+    #[serde(default = "white")]
+    pub floor_tint: (f32, f32, f32),
+    #[serde(default = "white")]
+    pub wall_tint: (f32, f32, f32),
+    #[serde(default)]
+    pub props: Vec<Prop>,
+}
+
+/// a decoration drawn over plain floor, it does not block anything
+#[derive(Clone, Deserialize)]
+pub struct Prop {
+    pub sprite: usize,
+    // chance of a floor tile getting the prop
+    pub chance: f64,
+}
+
+fn white() -> (f32, f32, f32) {
+    (1., 1., 1.)
+}
+
+impl Theme {
+    /// index into the tile atlas, tiles the theme doesn't restyle keep their own sprite
+    pub fn sprite_index(&self, kind: TileKind) -> usize {
+        match kind {
+            TileKind::Floor | TileKind::Door { .. } => self.floor_sprite,
+            TileKind::Wall { .. } | TileKind::SecretDoor => self.wall_sprite,
+            _ => kind.sprite_index(),
+        }
+    }
+
+    pub fn tint(&self, kind: TileKind) -> Color {
+        let (r, g, b) = match kind.blocks_vision() {
+            true => self.wall_tint,
+            false => self.floor_tint,
+        };
+        Color::rgb(r, g, b)
+    }
+}
+
+impl Themes {
+    pub fn get(&self, name: &str) -> Option<&Theme> {
+        self.themes.iter().find(|t| t.name == name)
+    }
+
+    /// rejects themes that would panic once a level is themed with them
+    pub fn validate(&self) -> Result<(), String> {
+        // the weights are summed up for every pick
+        self.themes
+            .iter()
+            .try_fold(0u32, |total, t| total.checked_add(t.weight))
+            .ok_or("the theme weights add up to more than a u32")?;
+        for theme in self.themes.iter() {
+            if theme.max_depth.is_some_and(|max| max < theme.min_depth) {
+                return Err(format!(
+                    "theme {}: max_depth is below min_depth",
+                    theme.name
+                ));
+            }
+            for prop in theme.props.iter() {
+                check_chance("prop chance", prop.chance)
+                    .map_err(|e| format!("theme {}: {}", theme.name, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// picks a weighted random theme that is allowed at the given depth
+    pub fn choose(&self, depth: u32, rng: &mut StdRng) -> Option<&Theme> {
+        let candidates = self
+            .themes
+            .iter()
+            .filter(|t| in_depth(t.min_depth, t.max_depth, depth))
+            .map(|t| (t, t.weight));
+        choose_weighted(candidates, rng)
+    }
+}
+
+/// the themes used on the current level
+///
+/// tiles use the first theme unless their area was given one of its own,
+/// without any theme tiles keep their default sprites
+#[derive(Clone, Default, Resource)]
+pub struct LevelThemes {
+    pub themes: Vec<Theme>,
+    // index into `themes`, only for tiles that don't use the first one
+    pub tiles: HashMap<Vector2Int, usize>,
+    // prop sprites by position
+    pub props: HashMap<Vector2Int, usize>,
+}
+
+impl LevelThemes {
+    pub fn at(&self, v: Vector2Int) -> Option<&Theme> {
+        self.themes.get(self.tiles.get(&v).copied().unwrap_or(0))
+    }
+
+    /// index into the tile atlas for a tile of the given kind at `v`
+    pub fn sprite_index(&self, v: Vector2Int, kind: TileKind) -> usize {
+        match self.at(v) {
+            Some(theme) => theme.sprite_index(kind),
+            None => kind.sprite_index(),
+        }
+    }
+
+    pub fn tint(&self, v: Vector2Int, kind: TileKind) -> Color {
+        match self.at(v) {
+            Some(theme) => theme.tint(kind),
+            None => Color::WHITE,
+        }
+    }
+
+    pub fn theme_name(&self, v: Vector2Int) -> Option<&str> {
+        self.at(v).map(|t| t.name.as_str())
+    }
+
+    /// names of every theme on the level, a single `None` when it has no theme
+    pub fn names(&self) -> Vec<Option<&str>> {
+        match self.themes.is_empty() {
+            true => vec![None],
+            false => self.themes.iter().map(|t| Some(t.name.as_str())).collect(),
+        }
+    }
+
+    /// themes a generated floor, the recipe and its areas can name their theme,
+    /// otherwise a random one is picked for the depth
+    ///
+    /// props are scattered over the valid spots with the floor's rng,
+    /// so a seed always decorates its floors the same way
+    pub fn generate(
+        dungeon: &Dungeon,
+        recipe: &DungeonRecipe,
+        themes: &Themes,
+        depth: u32,
+        rng: &mut StdRng,
+    ) -> Self {
+        let level_theme = match &recipe.theme {
+            Some(name) => themes.get(name),
+            None => themes.choose(depth, rng),
+        };
+        let Some(level_theme) = level_theme else {
+            return LevelThemes::default();
+        };
+        let mut level = LevelThemes {
+            themes: vec![level_theme.clone()],
+            ..Default::default()
+        };
+
+        for (area, area_recipe) in dungeon.areas.iter().zip(recipe.areas.iter()) {
+            let Some(theme) = area_recipe.theme.as_ref().and_then(|n| themes.get(n)) else {
+                continue;
+            };
+            let idx = match level.themes.iter().position(|t| t.name == theme.name) {
+                Some(idx) => idx,
+                None => {
+                    level.themes.push(theme.clone());
+                    level.themes.len() - 1
+                }
+            };
+            level
+                .tiles
+                .extend(area.to_tiles().into_iter().map(|v| (v, idx)));
+        }
+        // walls take the theme of the floor they border
+        let wall_themes = dungeon
+            .walls
+            .iter()
+            .copied()
+            .filter_map(|v| {
+                (-1..=1)
+                    .flat_map(|y| (-1..=1).map(move |x| Vector2Int::new(x, y)))
+                    .find_map(|d| level.tiles.get(&(v + d)))
+                    .map(|idx| (v, *idx))
+            })
+            .collect::<Vec<_>>();
+        level.tiles.extend(wall_themes);
+
+        for v in dungeon.get_valid_spots() {
+            let Some(theme) = level.at(v) else {
+                continue;
+            };
+            if let Some(prop) = theme.props.iter().find(|p| rng.gen_bool(p.chance)) {
+                level.props.insert(v, prop.sprite);
+            }
+        }
+        level
+    }
+}
+
+#[derive(Resource)]
+pub struct ThemesHandle(pub Handle<Themes>);
+
+#[derive(Default)]
+pub struct ThemesLoader;
+
+impl AssetLoader for ThemesLoader {
+    type Asset = Themes;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let themes = ron::de::from_bytes::<Themes>(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            themes
+                .validate()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            Ok(themes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["themes.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shipped() -> Themes {
+        ron::de::from_str(include_str!("../../assets/level.themes.ron")).unwrap()
+    }
+
+    #[test]
+    fn shipped_themes_are_valid() {
+        assert_eq!(shipped().validate(), Ok(()));
+    }
+
+    #[test]
+    fn bad_prop_chance_fails_validation() {
+        let mut themes = shipped();
+        themes.themes[0].props.push(Prop {
+            sprite: 0,
+            chance: 1.5,
+        });
+        assert!(themes.validate().is_err());
+    }
+
+    #[test]
+    fn bad_weights_fail_validation() {
+        let mut themes = shipped();
+        themes.themes[0].weight = u32::MAX;
+        assert!(themes.validate().is_err());
+    }
+}
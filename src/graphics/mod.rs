@@ -32,9 +32,15 @@ impl Plugin for GraphicsPlugin {
                 Update,
                 (
                     tiles::spawn_tile_renderer,
+                    tiles::update_tile_sprites,
+                    tiles::spawn_door_renderer,
+                    tiles::update_door_sprites,
                     visibility::update_visibility,
+                    visibility::reveal_pieces,
                     pieces::spawn_piece_renderer,
-                    visibility::update_tile_colors.after(visibility::update_visibility),
+                    visibility::update_tile_colors
+                        .after(visibility::update_visibility)
+                        .after(tiles::update_tile_sprites),
                 ),
             );
     }
@@ -4,11 +4,11 @@ use bevy::prelude::*;
 
 use crate::{
     actions::{
-        models::{MeleeHitAction, ProjectileFlyAction, WalkAction},
+        models::{MeleeHitAction, ProjectileFlyAction, TeleportAction, WalkAction},
         ActionExecutedEvent,
     },
     board::components::Position,
-    pieces::components::Piece,
+    pieces::components::{Hidden, Piece},
 };
 
 use super::{
@@ -18,15 +18,22 @@ use super::{
 
 pub fn spawn_piece_renderer(
     mut commands: Commands,
-    query: Query<(Entity, &Position, &Piece), Added<Piece>>,
+    query: Query<(Entity, &Position, &Piece, Has<Hidden>), Added<Piece>>,
     assets: Res<Ascii>,
 ) {
-    for (entity, position, piece) in query.iter() {
+    for (entity, position, piece, hidden) in query.iter() {
         let sprite_idx = match piece.kind.as_str() {
             "Player" => 25,
             "Coin" => 522,
-            "Portal" => 297,
+            "Stairs Down" => 296,
+            "Stairs Up" => 294,
             "Health" => 529,
+            "Key" => 571,
+            "Rat" => 423,
+            "Bat" => 418,
+            "Skeleton" => 323,
+            "Ghost" => 320,
+            "Trap" => 1064,
             _ => 269,
         };
         let sprite = Sprite {
@@ -41,7 +48,10 @@ pub fn spawn_piece_renderer(
                 sprite,
                 texture: assets.image.clone(),
                 transform: Transform::from_translation(v),
-
+                visibility: match hidden {
+                    true => Visibility::Hidden,
+                    false => Visibility::Inherited,
+                },
                 ..default()
             })
             .insert(TextureAtlas {
@@ -112,6 +122,16 @@ pub fn walk_animation(mut commands: Commands, mut ev_action: EventReader<ActionE
             });
         }
 
+        if let Some(action) = action.downcast_ref::<TeleportAction>() {
+            if let Some(mut entity) = commands.get_entity(action.0) {
+                entity.insert(PathAnimator {
+                    path: VecDeque::from([super::get_world_vec(action.1, PIECE_Z)]),
+                    instant: true,
+                    ..default()
+                });
+            }
+        }
+
         if let Some(action) = action.downcast_ref::<ProjectileFlyAction>() {
             if let Some(v) = action.path.front() {
                 let target = super::get_world_vec(*v, PIECE_Z);
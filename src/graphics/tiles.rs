@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
-use crate::board::components::{Position, Tile, Wall};
+use crate::board::components::{Door, Position, Tile, TileKind};
+use crate::board::theme::LevelThemes;
+use crate::vectors::Vector2Int;
 
 use super::{assets::Ascii, TILE_SIZE, TILE_Z};
 
@@ -22,15 +24,19 @@ pub fn setup(
     });
 }
 
+/// decoration sprite of a theme, drawn as a child of its tile
+#[derive(Component)]
+pub struct Prop;
+
 pub fn spawn_tile_renderer(
     mut commands: Commands,
-    tile_query: Query<(Entity, &Position), Added<Tile>>,
-    wall_query: Query<Entity, Added<Wall>>,
+    tile_query: Query<(Entity, &Position, &Tile), Added<Tile>>,
     assets: Res<Ascii>,
+    themes: Res<LevelThemes>,
 ) {
-    for (entity, position) in tile_query.iter() {
+    for (entity, position, tile) in tile_query.iter() {
         let sprite = Sprite {
-            color: Color::rgba(1., 1., 1., 0.5),
+            color: tile_color(&themes, position.v, tile.kind),
             custom_size: Some(Vec2::splat(TILE_SIZE)),
             ..default()
         };
@@ -45,12 +51,66 @@ pub fn spawn_tile_renderer(
                 ..Default::default()
             })
             .insert(TextureAtlas {
-                index: 1,
+                index: themes.sprite_index(position.v, tile.kind),
                 layout: assets.texture.clone(),
             });
+
+        let Some(prop) = themes.props.get(&position.v) else {
+            continue;
+        };
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Prop,
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::splat(TILE_SIZE)),
+                        ..default()
+                    },
+                    texture: assets.image.clone(),
+                    transform: Transform::from_xyz(0., 0., 0.5),
+                    ..Default::default()
+                },
+                TextureAtlas {
+                    index: *prop,
+                    layout: assets.texture.clone(),
+                },
+            ));
+        });
+    }
+}
+
+/// walls are drawn opaque, the floor tiles faded
+fn tile_color(themes: &LevelThemes, v: Vector2Int, kind: TileKind) -> Color {
+    let alpha = match kind.blocks_vision() {
+        true => 1.,
+        false => 0.5,
+    };
+    themes.tint(v, kind).with_a(alpha)
+}
+
+/// swaps the sprite and tint of tiles whose kind changed, eg. a wall that was dug out
+pub fn update_tile_sprites(
+    mut query: Query<(&Position, &Tile, &mut TextureAtlas, &mut Sprite), Changed<Tile>>,
+    themes: Res<LevelThemes>,
+) {
+    for (position, tile, mut atlas, mut sprite) in query.iter_mut() {
+        let index = themes.sprite_index(position.v, tile.kind);
+        if atlas.index != index {
+            atlas.index = index;
+        }
+        let color = tile_color(&themes, position.v, tile.kind);
+        if sprite.color != color {
+            sprite.color = color;
+        }
     }
+}
 
-    for entity in wall_query.iter() {
+pub fn spawn_door_renderer(
+    mut commands: Commands,
+    query: Query<(Entity, &Door), Added<Door>>,
+    assets: Res<Ascii>,
+) {
+    for (entity, door) in query.iter() {
         let sprite = Sprite {
             custom_size: Some(Vec2::splat(TILE_SIZE)),
             ..default()
@@ -64,10 +124,16 @@ pub fn spawn_tile_renderer(
         };
 
         let texture_atlas = TextureAtlas {
-            index: 49,
+            index: door.sprite_index(),
             layout: assets.texture.clone(),
         };
 
         commands.entity(entity).insert(bundle).insert(texture_atlas);
     }
 }
+
+pub fn update_door_sprites(mut query: Query<(&Door, &mut TextureAtlas), Changed<Door>>) {
+    for (door, mut atlas) in query.iter_mut() {
+        atlas.index = door.sprite_index();
+    }
+}
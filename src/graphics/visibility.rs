@@ -1,14 +1,20 @@
 use bevy::prelude::*;
 
 use crate::{
-    board::components::{Position, Tile},
-    pieces::components::Piece,
+    board::{
+        components::{Position, Tile},
+        CurrentBoard,
+    },
+    pieces::components::{Hidden, Piece},
 };
 
 #[allow(clippy::type_complexity)]
 pub fn update_visibility(
     mut query: Query<(&mut Visibility, &Position, &Tile), Changed<Tile>>,
-    mut piece_query: Query<(&Position, &mut Visibility), (With<Piece>, Without<Tile>)>,
+    mut piece_query: Query<
+        (&Position, &mut Visibility),
+        (With<Piece>, Without<Tile>, Without<Hidden>),
+    >,
 ) {
     for (mut tile_visibility, tile_pos, tile) in query.iter_mut() {
         if tile.visible || tile.seen {
@@ -30,6 +36,28 @@ pub fn update_visibility(
     }
 }
 
+/// shows the pieces that were just found, if their tile is in view
+pub fn reveal_pieces(
+    mut removed: RemovedComponents<Hidden>,
+    mut piece_query: Query<(&Position, &mut Visibility), With<Piece>>,
+    tile_query: Query<&Tile>,
+    board: Res<CurrentBoard>,
+) {
+    for entity in removed.read() {
+        let Ok((position, mut visibility)) = piece_query.get_mut(entity) else {
+            continue;
+        };
+        let visible = board
+            .get_tile(position.v)
+            .and_then(|tile| tile_query.get(tile).ok())
+            .is_some_and(|tile| tile.visible);
+        *visibility = match visible {
+            true => Visibility::Visible,
+            false => Visibility::Hidden,
+        };
+    }
+}
+
 pub fn update_tile_colors(
     mut query: Query<(&mut Sprite, Option<&Children>, &Tile), Changed<Tile>>,
     mut sprite_query: Query<&mut Sprite, Without<Tile>>,
@@ -1,28 +1,56 @@
 use std::collections::VecDeque;
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::actions::models::{DigAction, MeleeHitAction, WalkAction};
+use crate::actions::models::{
+    CloseDoorAction, DigAction, DisarmTrapAction, FireAction, MeleeHitAction, OpenDoorAction,
+    SearchAction, WalkAction,
+};
 use crate::actions::{Action, ActorQueue};
 use crate::board::components::Position;
 use crate::graphics::assets::Ascii;
 use crate::graphics::TILE_SIZE;
 use crate::pieces::components::{Actor, Melee};
-use crate::pieces::spawn_projectile;
 use crate::player::Player;
+use crate::replay::Replay;
 use crate::states::GameState;
 use crate::vectors::Vector2Int;
+use targeting::{
+    aim, clear_shot_preview, start_targeting, update_shot_preview, Aim, TargetingState,
+};
+
+pub mod targeting;
 
 pub struct InputPlugin;
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<PlayerInputReadyEvent>()
+            .add_event::<PlayerCommandEvent>()
             .init_state::<ActionDirectionSelectionState>()
+            .init_state::<TargetingState>()
+            .init_resource::<Aim>()
+            .add_systems(
+                Update,
+                (
+                    (read_player_input, start_targeting)
+                        .run_if(in_state(TargetingState::None))
+                        .run_if(not(resource_exists::<Replay>)),
+                    aim.run_if(in_state(TargetingState::Aiming)),
+                )
+                    .in_set(ReadInputSet)
+                    .run_if(in_state(GameState::PlayerInput)),
+            )
             .add_systems(
                 Update,
-                player_position.run_if(in_state(GameState::PlayerInput)),
+                (
+                    apply_player_command.after(ReadInputSet),
+                    update_shot_preview.run_if(in_state(TargetingState::Aiming)),
+                )
+                    .run_if(in_state(GameState::PlayerInput)),
             )
+            .add_systems(OnExit(TargetingState::Aiming), clear_shot_preview)
             .add_systems(
                 OnEnter(ActionDirectionSelectionState::Pending),
                 display_action_arrows,
@@ -55,22 +83,38 @@ pub enum ActionDirectionSelectionState {
 #[derive(Event)]
 pub struct PlayerInputReadyEvent;
 
+/// systems turning keyboard and mouse input into `PlayerCommandEvent`s
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ReadInputSet;
+
+/// what the player decided to do on their turn, directions are relative to the player
+///
+/// commands come from the keyboard or from a replay and are what a recording stores
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerCommand {
+    // walk, attack or open a door in the direction
+    Step(Vector2Int),
+    Dig(Vector2Int),
+    CloseDoor,
+    Search,
+    DisarmTrap,
+    // fire the ranged weapon at a tile, picked in the targeting mode
+    Shoot(Vector2Int),
+}
+
+#[derive(Event)]
+pub struct PlayerCommandEvent(pub PlayerCommand);
+
 #[derive(Component)]
 pub struct Arrows;
 
-fn player_position(
-    mut commands: Commands,
+/// reads the keyboard into a command for the player's turn
+pub fn read_player_input(
     keys: ResMut<ButtonInput<KeyCode>>,
-    mut player_query: Query<(Entity, &Position, &Melee, &mut Actor), With<Player>>,
-    mut queue: ResMut<ActorQueue>,
     mut next_state: ResMut<NextState<ActionDirectionSelectionState>>,
     state: Res<State<ActionDirectionSelectionState>>,
-    mut ev_input: EventWriter<PlayerInputReadyEvent>,
+    mut ev_command: EventWriter<PlayerCommandEvent>,
 ) {
-    let Ok((entity, position, melee, mut actor)) = player_query.get_single_mut() else {
-        return;
-    };
-
     if keys.just_pressed(KeyCode::Escape) {
         next_state.set(ActionDirectionSelectionState::None);
         return;
@@ -81,52 +125,104 @@ fn player_position(
         return;
     }
 
-    if keys.just_pressed(KeyCode::KeyR) {
-        let source = Vector2Int::new(position.v.x + 1, position.v.y);
-        let destination = Vector2Int::new(position.v.x + 10, position.v.y);
-
-        spawn_projectile(&mut commands, source, destination);
-        ev_input.send(PlayerInputReadyEvent);
+    if keys.just_pressed(KeyCode::KeyC) {
+        ev_command.send(PlayerCommandEvent(PlayerCommand::CloseDoor));
         return;
     }
 
-    if state.get() == &ActionDirectionSelectionState::Pending {
-        for (key, dir) in DIR_KEY_MAPPING {
-            if !keys.just_pressed(key.0) && !keys.just_pressed(key.1) {
-                continue;
-            }
+    if keys.just_pressed(KeyCode::KeyE) {
+        ev_command.send(PlayerCommandEvent(PlayerCommand::Search));
+        return;
+    }
 
-            let dig_action: (Box<dyn Action>, i32) =
-                (Box::new(DigAction(entity, position.v + dir)), 0);
-            actor.0 = vec![dig_action];
-            queue.0 = VecDeque::from([entity]);
-            ev_input.send(PlayerInputReadyEvent);
-            next_state.set(ActionDirectionSelectionState::None);
-            return;
-        }
+    if keys.just_pressed(KeyCode::KeyX) {
+        ev_command.send(PlayerCommandEvent(PlayerCommand::DisarmTrap));
+        return;
     }
 
     for (key, dir) in DIR_KEY_MAPPING {
         if !keys.just_pressed(key.0) && !keys.just_pressed(key.1) {
             continue;
         }
-        if *state.get() != ActionDirectionSelectionState::None {
-            continue;
-        }
+        let command = match state.get() {
+            ActionDirectionSelectionState::Pending => {
+                next_state.set(ActionDirectionSelectionState::None);
+                PlayerCommand::Dig(dir)
+            }
+            ActionDirectionSelectionState::None => PlayerCommand::Step(dir),
+        };
+        ev_command.send(PlayerCommandEvent(command));
+        return;
+    }
+}
 
-        let move_action: (Box<dyn Action>, i32) =
-            (Box::new(WalkAction(entity, position.v + dir)), 0);
+/// turns the player's command into the actions they try this turn
+pub fn apply_player_command(
+    mut player_query: Query<(Entity, &Position, &Melee, &mut Actor), With<Player>>,
+    mut queue: ResMut<ActorQueue>,
+    mut ev_command: EventReader<PlayerCommandEvent>,
+    mut ev_input: EventWriter<PlayerInputReadyEvent>,
+) {
+    let Ok((entity, position, melee, mut actor)) = player_query.get_single_mut() else {
+        return;
+    };
 
-        let melee_action: (Box<dyn Action>, i32) = (
-            Box::new(MeleeHitAction {
-                attacker: entity,
-                target: position.v + dir,
-                damage: melee.current_damage.min,
-            }),
-            0,
-        );
+    for PlayerCommandEvent(command) in ev_command.read() {
+        match *command {
+            PlayerCommand::Shoot(target) => {
+                actor.0 = vec![(Box::new(FireAction(entity, position.v + target)), 0)];
+            }
+            PlayerCommand::CloseDoor => {
+                // close the first open door next to the player
+                actor.0 = DIR_KEY_MAPPING
+                    .iter()
+                    .map(|(_, dir)| {
+                        (
+                            Box::new(CloseDoorAction(entity, position.v + *dir)) as Box<dyn Action>,
+                            0,
+                        )
+                    })
+                    .collect();
+            }
+            PlayerCommand::Search => {
+                actor.0 = vec![(Box::new(SearchAction(entity)), 0)];
+            }
+            PlayerCommand::DisarmTrap => {
+                // disarm the first known trap under or next to the player
+                actor.0 = std::iter::once(Vector2Int::new(0, 0))
+                    .chain(DIR_KEY_MAPPING.iter().map(|(_, dir)| *dir))
+                    .map(|dir| {
+                        (
+                            Box::new(DisarmTrapAction(entity, position.v + dir)) as Box<dyn Action>,
+                            0,
+                        )
+                    })
+                    .collect();
+            }
+            PlayerCommand::Dig(dir) => {
+                actor.0 = vec![(Box::new(DigAction(entity, position.v + dir)), 0)];
+            }
+            PlayerCommand::Step(dir) => {
+                let move_action: (Box<dyn Action>, i32) =
+                    (Box::new(WalkAction(entity, position.v + dir)), 0);
+
+                let melee_action: (Box<dyn Action>, i32) = (
+                    Box::new(MeleeHitAction {
+                        attacker: entity,
+                        target: position.v + dir,
+                        damage: melee.current_damage.min,
+                        cost: melee.attack_cost,
+                    }),
+                    0,
+                );
+
+                // walking into a closed door opens it
+                let open_action: (Box<dyn Action>, i32) =
+                    (Box::new(OpenDoorAction(entity, position.v + dir)), 0);
 
-        actor.0 = vec![move_action, melee_action];
+                actor.0 = vec![move_action, melee_action, open_action];
+            }
+        }
         queue.0 = VecDeque::from([entity]);
         ev_input.send(PlayerInputReadyEvent);
     }
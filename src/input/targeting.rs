@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+
+use crate::actions::models::shot_path;
+use crate::board::components::{Position, Tile};
+use crate::board::CurrentBoard;
+use crate::graphics::{get_world_position, TILE_SIZE};
+use crate::messages::{MessageLog, Severity};
+use crate::pieces::components::{Actor, Health, Occupier};
+use crate::pieces::equipment::Equipment;
+use crate::player::Player;
+use crate::vectors::{Grid, Vector2Int};
+
+use super::{PlayerCommand, PlayerCommandEvent, DIR_KEY_MAPPING};
+
+const PREVIEW_Z: f32 = 50.;
+
+/// aiming a ranged weapon, entered with `R`
+#[derive(Clone, Debug, Default, Hash, Eq, States, PartialEq)]
+pub enum TargetingState {
+    #[default]
+    None,
+    Aiming,
+}
+
+/// the tile the player aims at
+#[derive(Default, Resource)]
+pub struct Aim {
+    pub target: Vector2Int,
+}
+
+#[derive(Component)]
+pub struct ShotPreview;
+
+type HostileFilter = (With<Actor>, With<Health>, Without<Player>);
+
+fn ranged_weapon(equipment: &Equipment) -> Option<u32> {
+    equipment
+        .weapon
+        .as_ref()
+        .and_then(|item| item.as_equippable())
+        .and_then(|weapon| weapon.range())
+}
+
+/// npcs on tiles the player can see, closest first
+fn visible_hostiles(
+    from: Vector2Int,
+    hostile_query: &Query<&Position, HostileFilter>,
+    tile_query: &Query<(&Tile, &Position)>,
+    board: &CurrentBoard,
+) -> Vec<Vector2Int> {
+    let mut hostiles = hostile_query
+        .iter()
+        .map(|p| p.v)
+        .filter(|v| {
+            board
+                .get_tile(*v)
+                .and_then(|tile| tile_query.get(tile).ok())
+                .is_some_and(|(tile, _)| tile.visible)
+        })
+        .collect::<Vec<_>>();
+    hostiles.sort_by_key(|v| (v.distance(from), *v));
+    hostiles
+}
+
+/// `R` starts aiming if the player holds a ranged weapon
+#[allow(clippy::too_many_arguments)]
+pub fn start_targeting(
+    keys: Res<ButtonInput<KeyCode>>,
+    player_query: Query<(&Position, &Equipment), With<Player>>,
+    hostile_query: Query<&Position, HostileFilter>,
+    tile_query: Query<(&Tile, &Position)>,
+    board: Res<CurrentBoard>,
+    mut aim: ResMut<Aim>,
+    mut next_state: ResMut<NextState<TargetingState>>,
+    mut log: ResMut<MessageLog>,
+) {
+    if !keys.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    let Ok((position, equipment)) = player_query.get_single() else {
+        return;
+    };
+    if ranged_weapon(equipment).is_none() {
+        log.add(Severity::Warning, "you need a ranged weapon to shoot");
+        return;
+    }
+    // start on the closest npc in sight
+    aim.target = visible_hostiles(position.v, &hostile_query, &tile_query, &board)
+        .first()
+        .copied()
+        .unwrap_or(position.v + Vector2Int::RIGHT);
+    next_state.set(TargetingState::Aiming);
+}
+
+/// moves the aim with the direction keys, the mouse or `Tab` through the npcs in sight,
+/// `R`, `Enter` or a click fires and `Escape` cancels
+#[allow(clippy::too_many_arguments)]
+pub fn aim(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut ev_cursor: EventReader<CursorMoved>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    player_query: Query<&Position, With<Player>>,
+    hostile_query: Query<&Position, HostileFilter>,
+    tile_query: Query<(&Tile, &Position)>,
+    board: Res<CurrentBoard>,
+    mut aim: ResMut<Aim>,
+    mut next_state: ResMut<NextState<TargetingState>>,
+    mut ev_command: EventWriter<PlayerCommandEvent>,
+) {
+    let Ok(position) = player_query.get_single() else {
+        return;
+    };
+
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(TargetingState::None);
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::KeyR)
+        || keys.just_pressed(KeyCode::Enter)
+        || mouse.just_pressed(MouseButton::Left)
+    {
+        ev_command.send(PlayerCommandEvent(PlayerCommand::Shoot(
+            aim.target - position.v,
+        )));
+        next_state.set(TargetingState::None);
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        let hostiles = visible_hostiles(position.v, &hostile_query, &tile_query, &board);
+        // the one after the current target, wrapping around
+        let next = hostiles
+            .iter()
+            .position(|v| *v == aim.target)
+            .map_or(0, |i| (i + 1) % hostiles.len().max(1));
+        if let Some(v) = hostiles.get(next) {
+            aim.target = *v;
+        }
+    }
+
+    for (key, dir) in DIR_KEY_MAPPING {
+        if keys.just_pressed(key.0) || keys.just_pressed(key.1) {
+            aim.target += dir;
+        }
+    }
+
+    let Some(cursor) = ev_cursor.read().last() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+        return;
+    };
+    if let Some(world) = camera.viewport_to_world_2d(camera_transform, cursor.position) {
+        let v = Vector2Int::new(
+            (world.x / TILE_SIZE).round() as i32,
+            (world.y / TILE_SIZE).round() as i32,
+        );
+        if v != aim.target {
+            aim.target = v;
+        }
+    }
+}
+
+/// draws the path the shot would take, a blocked shot ends on a red tile
+///
+/// blockers match the ones `ProjectileShootAction` uses
+pub fn update_shot_preview(
+    mut commands: Commands,
+    aim: Res<Aim>,
+    player_query: Query<(&Position, &Equipment), With<Player>>,
+    occupier_query: Query<&Position, With<Occupier>>,
+    tile_query: Query<(&Tile, &Position)>,
+    preview_query: Query<Entity, With<ShotPreview>>,
+    board: Res<CurrentBoard>,
+) {
+    if !aim.is_changed() {
+        return;
+    }
+    for entity in preview_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Ok((position, equipment)) = player_query.get_single() else {
+        return;
+    };
+    let Some(range) = ranged_weapon(equipment) else {
+        return;
+    };
+
+    let (min, max) = board.tiles.bounds();
+    let mut blockers = Grid::new(min, max, false);
+    for p in occupier_query.iter() {
+        blockers.set(p.v, true);
+    }
+    for (tile, p) in tile_query.iter() {
+        if tile.kind.blocks_vision() {
+            blockers.set(p.v, true);
+        }
+    }
+    let path = shot_path(position.v, aim.target, range, blockers.clone());
+    let blocked = path.last().is_some_and(|v| blockers.get(*v) == Some(&true));
+
+    for (i, v) in path.iter().enumerate() {
+        let color = match blocked && i == path.len() - 1 {
+            true => Color::rgba(0.94, 0.35, 0.25, 0.6),
+            false => Color::rgba(0.95, 0.8, 0.3, 0.35),
+        };
+        spawn_preview_tile(&mut commands, *v, color);
+    }
+    // the aimed at tile is marked even when the shot falls short of it
+    if path.last() != Some(&aim.target) {
+        spawn_preview_tile(&mut commands, aim.target, Color::rgba(1., 1., 1., 0.25));
+    }
+}
+
+fn spawn_preview_tile(commands: &mut Commands, v: Vector2Int, color: Color) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(TILE_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(get_world_position(&Position { v }, PREVIEW_Z)),
+            ..default()
+        },
+        ShotPreview,
+    ));
+}
+
+pub fn clear_shot_preview(mut commands: Commands, preview_query: Query<Entity, With<ShotPreview>>) {
+    for entity in preview_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
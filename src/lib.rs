@@ -0,0 +1,17 @@
+pub mod camera;
+
+pub mod actions;
+pub mod board;
+pub mod globals;
+pub mod graphics;
+pub mod input;
+pub mod manager;
+pub mod menus;
+pub mod messages;
+pub mod options;
+pub mod pieces;
+pub mod player;
+pub mod replay;
+pub mod states;
+pub mod ui;
+pub mod vectors;
@@ -1,23 +1,21 @@
-mod camera;
-
-mod actions;
-mod board;
-mod globals;
-mod graphics;
-mod input;
-mod manager;
-mod menus;
-mod pieces;
-mod player;
-mod states;
-mod ui;
-mod vectors;
-
 use belly::build::BellyPlugin;
 use bevy::prelude::*;
-use states::{GameState, MainState};
+use roguelike::{
+    actions, board, camera, globals, graphics, input, manager, menus, messages, options, pieces,
+    player, replay,
+    states::{GameState, MainState},
+    ui,
+};
 
 fn main() {
+    let options = match options::LaunchOptions::from_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     App::new()
         .add_plugins(
             DefaultPlugins
@@ -34,9 +32,11 @@ fn main() {
         .init_state::<MainState>()
         .init_state::<GameState>()
         .insert_resource(Msaa::Off)
+        .insert_resource(options)
         .add_plugins(BellyPlugin)
         .add_plugins(camera::CameraPlugin)
         .add_plugins(actions::ActionsPlugin)
+        .add_plugins(messages::MessagesPlugin)
         .add_plugins(ui::UiPlugin)
         .add_plugins(board::BoardPlugin)
         .add_plugins(graphics::GraphicsPlugin)
@@ -45,5 +45,6 @@ fn main() {
         .add_plugins(player::PlayerPlugin)
         .add_plugins(pieces::PiecesPlugin)
         .add_plugins(menus::MenuPlugin)
+        .add_plugins(replay::ReplayPlugin)
         .run();
 }
@@ -1,12 +1,15 @@
 use bevy::prelude::*;
 
-use crate::actions::{
-    ActionsCompleteEvent, GameOverEvent, InvalidPlayerActionEvent, NextLevelEvent, TickEvent,
-};
-use crate::board::systems::{despawn_map, spawn_map};
+use crate::actions::{ActionsCompleteEvent, GameOverEvent, InvalidPlayerActionEvent, TickEvent};
+use crate::board::systems::{change_depth, despawn_map, level_changed, save_level, spawn_map};
+use crate::board::FixedSpawns;
 use crate::graphics::GraphicsWaitEvent;
 use crate::input::PlayerInputReadyEvent;
-use crate::pieces::{despawn_pieces, spawn_npcs};
+use crate::messages::{MessageLog, Severity};
+use crate::pieces::{
+    despawn_pieces, generated_level, restore_pieces, save_pieces, spawn_fixed_pieces, spawn_keys,
+    spawn_npcs, spawn_stairs, SavedPieces,
+};
 use crate::player::randomly_reposition_player;
 use crate::states::{GameState, LevelSetupSet, MainState, TurnSet};
 
@@ -22,12 +25,11 @@ impl Plugin for ManagerPlugin {
                     .chain()
                     .run_if(in_state(GameState::TurnUpdate)),
             )
-            .configure_sets(Update, LevelSetupSet.run_if(on_event::<NextLevelEvent>()))
+            .configure_sets(Update, LevelSetupSet.run_if(level_changed))
             .add_systems(
                 Update,
                 turn_update_start.run_if(on_event::<PlayerInputReadyEvent>()),
             )
-            .add_systems(Update, next_level.run_if(on_event::<NextLevelEvent>()))
             .add_systems(Update, game_over.run_if(on_event::<GameOverEvent>()))
             .add_systems(
                 Update,
@@ -41,11 +43,18 @@ impl Plugin for ManagerPlugin {
             .add_systems(
                 Update,
                 (
+                    save_level,
+                    save_pieces,
+                    change_depth,
                     despawn_map,
                     despawn_pieces,
                     spawn_map,
                     randomly_reposition_player,
-                    spawn_npcs,
+                    spawn_npcs.run_if(generated_level),
+                    spawn_fixed_pieces.run_if(resource_exists::<FixedSpawns>),
+                    restore_pieces.run_if(resource_exists::<SavedPieces>),
+                    spawn_keys,
+                    spawn_stairs,
                 )
                     .chain()
                     .in_set(LevelSetupSet),
@@ -69,12 +78,6 @@ fn turn_update_start(
     ev_tick.send(TickEvent);
 }
 
-fn next_level(mut ev_next_level: EventReader<NextLevelEvent>) {
-    for _ in ev_next_level.read() {
-        println!("Next Level event recieved");
-    }
-}
-
 fn tick(mut ev_wait: EventReader<GraphicsWaitEvent>, mut ev_tick: EventWriter<TickEvent>) {
     if ev_wait.read().len() == 0 {
         ev_tick.send(TickEvent);
@@ -89,6 +92,13 @@ fn turn_update_end(mut next_state: ResMut<NextState<GameState>>) {
     next_state.set(GameState::PlayerInput);
 }
 
-fn turn_update_cancel(mut next_state: ResMut<NextState<GameState>>) {
+fn turn_update_cancel(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut ev_invalid: EventReader<InvalidPlayerActionEvent>,
+    mut log: ResMut<MessageLog>,
+) {
+    for ev in ev_invalid.read() {
+        log.add(Severity::Warning, ev.0.to_string());
+    }
     next_state.set(GameState::PlayerInput);
 }
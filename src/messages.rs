@@ -0,0 +1,251 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::{
+    actions::{
+        models::{
+            DisarmTrapAction, FallAction, RevealSecretDoorAction, TeleportAction, TriggerTrapAction,
+        },
+        ActionExecutedEvent,
+    },
+    pieces::components::{Piece, Trap, TrapKind},
+    player::Player,
+    states::MainState,
+};
+
+// older messages are dropped once the log is this long
+const MAX_MESSAGES: usize = 200;
+
+pub struct MessagesPlugin;
+
+impl Plugin for MessagesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MessageLog>()
+            .add_systems(OnEnter(MainState::Game), clear_log)
+            .add_systems(
+                Update,
+                log_actions.run_if(on_event::<ActionExecutedEvent>()),
+            );
+    }
+}
+
+/// decides the color a message is shown in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Combat,
+    // something the player tried did not work
+    Warning,
+    // the player is getting hurt
+    Danger,
+}
+
+impl Severity {
+    pub fn color(&self) -> Color {
+        match self {
+            Severity::Info => Color::rgb(0.7, 0.7, 0.7),
+            Severity::Combat => Color::rgb(0.86, 0.85, 0.86),
+            Severity::Warning => Color::rgb(0.95, 0.8, 0.3),
+            Severity::Danger => Color::rgb(0.94, 0.35, 0.25),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub text: String,
+    pub severity: Severity,
+}
+
+/// everything the player was told during the current game, oldest first
+#[derive(Default, Resource)]
+pub struct MessageLog {
+    messages: VecDeque<Message>,
+}
+
+impl MessageLog {
+    /// adds a message, its first letter is capitalized
+    pub fn add(&mut self, severity: Severity, text: impl Into<String>) {
+        let text = text.into();
+        let mut chars = text.chars();
+        let text = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => text,
+        };
+        if self.messages.len() >= MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(Message { text, severity });
+    }
+
+    pub fn messages(&self) -> &VecDeque<Message> {
+        &self.messages
+    }
+
+    /// up to `lines` messages, ending `scroll` messages before the newest one
+    pub fn page(&self, scroll: usize, lines: usize) -> impl Iterator<Item = &Message> {
+        let end = self.messages.len().saturating_sub(scroll);
+        let start = end.saturating_sub(lines);
+        self.messages.range(start..end)
+    }
+
+    pub fn last(&self) -> Option<&Message> {
+        self.messages.back()
+    }
+
+    /// whether any message contains the given text
+    pub fn contains(&self, text: &str) -> bool {
+        self.messages.iter().any(|m| m.text.contains(text))
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
+
+/// adds a message from code that only has the world at hand, like actions
+pub fn log(world: &mut World, severity: Severity, text: impl Into<String>) {
+    if let Some(mut log) = world.get_resource_mut::<MessageLog>() {
+        log.add(severity, text);
+    }
+}
+
+/// how an entity is called in messages, "you" for the player
+pub fn entity_name(world: &World, entity: Entity) -> String {
+    piece_name(
+        world.get::<Piece>(entity),
+        world.get::<Player>(entity).is_some(),
+    )
+}
+
+fn piece_name(piece: Option<&Piece>, is_player: bool) -> String {
+    match (piece, is_player) {
+        (_, true) => "you".to_string(),
+        (Some(piece), false) => format!("the {}", piece.kind.to_lowercase()),
+        (None, false) => "something".to_string(),
+    }
+}
+
+fn trap_name(kind: TrapKind) -> &'static str {
+    match kind {
+        TrapKind::Spike => "a spike trap",
+        TrapKind::Teleport => "a teleport trap",
+        TrapKind::Alarm => "an alarm trap",
+        TrapKind::PoisonGas => "a poison gas trap",
+    }
+}
+
+fn clear_log(mut log: ResMut<MessageLog>) {
+    log.clear();
+}
+
+/// describes the actions that leave enough behind to be told about afterwards,
+/// the others log themselves while they are executed
+fn log_actions(
+    mut ev_action: EventReader<ActionExecutedEvent>,
+    mut log: ResMut<MessageLog>,
+    piece_query: Query<(Option<&Piece>, Has<Player>)>,
+    trap_query: Query<&Trap>,
+) {
+    let is_player = |entity| {
+        piece_query
+            .get(entity)
+            .map(|(_, player)| player)
+            .unwrap_or(false)
+    };
+    for ev in ev_action.read() {
+        let action = ev.0.as_any();
+        if let Some(action) = action.downcast_ref::<TriggerTrapAction>() {
+            let (Ok((piece, player)), Ok(trap)) =
+                (piece_query.get(action.0), trap_query.get(action.1))
+            else {
+                continue;
+            };
+            let severity = match player {
+                true => Severity::Danger,
+                false => Severity::Info,
+            };
+            let verb = match player {
+                true => "trigger",
+                false => "triggers",
+            };
+            log.add(
+                severity,
+                format!(
+                    "{} {} {}",
+                    piece_name(piece, player),
+                    verb,
+                    trap_name(trap.kind)
+                ),
+            );
+        }
+        if let Some(action) = action.downcast_ref::<DisarmTrapAction>() {
+            if is_player(action.0) {
+                log.add(Severity::Info, "you disarm the trap");
+            }
+        }
+        if action.downcast_ref::<RevealSecretDoorAction>().is_some() {
+            log.add(Severity::Info, "a secret door is revealed");
+        }
+        if let Some(action) = action.downcast_ref::<TeleportAction>() {
+            if is_player(action.0) {
+                log.add(Severity::Info, "you are teleported away");
+            }
+        }
+        if let Some(action) = action.downcast_ref::<FallAction>() {
+            if is_player(action.0) {
+                log.add(Severity::Danger, "you fall through the floor");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_capitalized() {
+        let mut log = MessageLog::default();
+        log.add(Severity::Info, "you find a key");
+        log.add(Severity::Info, "");
+        assert_eq!(log.messages()[0].text, "You find a key");
+        assert_eq!(log.last().unwrap().text, "");
+    }
+
+    #[test]
+    fn oldest_messages_are_dropped_at_capacity() {
+        let mut log = MessageLog::default();
+        for i in 0..MAX_MESSAGES + 5 {
+            log.add(Severity::Info, i.to_string());
+        }
+        assert_eq!(log.len(), MAX_MESSAGES);
+        assert_eq!(log.messages()[0].text, "5");
+        assert_eq!(log.last().unwrap().text, (MAX_MESSAGES + 4).to_string());
+    }
+
+    #[test]
+    fn pages_scroll_back_from_the_newest() {
+        let mut log = MessageLog::default();
+        for i in 0..10 {
+            log.add(Severity::Info, i.to_string());
+        }
+        let page = |scroll| {
+            log.page(scroll, 4)
+                .map(|m| m.text.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(page(0), ["6", "7", "8", "9"]);
+        assert_eq!(page(4), ["2", "3", "4", "5"]);
+        assert_eq!(page(8), ["0", "1"]);
+        assert!(page(20).is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+
+/// settings passed on the command line when launching the game
+///
+/// `--seed <u64>` starts every run from the given dungeon seed
+///
+/// `--map <level>=<path>` plays a hand-authored map (relative to `assets/`) on the given level,
+/// can be repeated
+///
+/// `--record <path>` writes the run's seed and every player command to a replay file
+///
+/// `--replay <path>` plays a replay file back instead of reading the keyboard,
+/// `--replay-speed <f32>` scales how fast it runs, it has to be positive
+#[derive(Resource, Default)]
+pub struct LaunchOptions {
+    pub seed: Option<u64>,
+    pub maps: Vec<(u32, String)>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub replay_speed: Option<f32>,
+}
+
+impl LaunchOptions {
+    /// reads the options from the command line, an invalid value is an error
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().ok_or("Missing value for --seed")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("Invalid seed {}, expected a number", value))?;
+                    options.seed = Some(seed);
+                }
+                "--record" => {
+                    options.record = Some(args.next().ok_or("Missing value for --record")?);
+                }
+                "--replay" => {
+                    options.replay = Some(args.next().ok_or("Missing value for --replay")?);
+                }
+                "--replay-speed" => {
+                    let value = args.next().ok_or("Missing value for --replay-speed")?;
+                    // time can't run backwards or stand still
+                    let speed = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|s| s.is_finite() && *s > 0.)
+                        .ok_or(format!(
+                            "Invalid replay speed {}, expected a positive number",
+                            value
+                        ))?;
+                    options.replay_speed = Some(speed);
+                }
+                "--map" => {
+                    let value = args.next().ok_or("Missing value for --map")?;
+                    let map = value
+                        .split_once('=')
+                        .and_then(|(level, path)| Some((level.parse().ok()?, path.to_string())))
+                        .ok_or(format!("Invalid map {}, expected <level>=<path>", value))?;
+                    options.maps.push(map);
+                }
+                _ => (),
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<LaunchOptions, String> {
+        LaunchOptions::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn reads_valid_options() {
+        let options = parse(&[
+            "--seed",
+            "12",
+            "--replay",
+            "run.ron",
+            "--replay-speed",
+            "2.5",
+        ]);
+        let options = options.unwrap();
+        assert_eq!(options.seed, Some(12));
+        assert_eq!(options.replay.as_deref(), Some("run.ron"));
+        assert_eq!(options.replay_speed, Some(2.5));
+    }
+
+    #[test]
+    fn rejects_bad_replay_speeds() {
+        for speed in ["-2", "0", "abc", "inf", "NaN"] {
+            assert!(parse(&["--replay-speed", speed]).is_err(), "{}", speed);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        for arg in ["--seed", "--record", "--replay", "--replay-speed", "--map"] {
+            assert!(parse(&[arg]).is_err(), "{}", arg);
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::{
-    actions::{models::despawn_recursive, Action},
+    actions::{models::despawn_recursive, Action, ActionError},
+    player::inventory::Inventory,
     vectors::Vector2Int,
 };
 
@@ -10,6 +12,24 @@ use super::equipment::{Equippable, Item};
 #[derive(Component, Default)]
 pub struct Actor(pub Vec<(Box<dyn Action>, i32)>);
 
+/// energy an actor gains whenever time passes, `NORMAL_SPEED` acts once per player turn
+#[derive(Component, Clone, Copy)]
+pub struct Speed(pub u32);
+
+pub const NORMAL_SPEED: u32 = 100;
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed(NORMAL_SPEED)
+    }
+}
+
+/// actors act while their energy lasts, every action spends its cost
+///
+/// can go negative after a costly action, the actor then sits out until it has recovered
+#[derive(Component, Default)]
+pub struct Energy(pub i32);
+
 #[derive(Component)]
 pub struct Health {
     pub base: Range,
@@ -47,6 +67,8 @@ pub struct GoldDrop {
 pub struct Projectile {
     pub destination: Vector2Int,
     pub damage: Damage,
+    // tiles it flies before dropping
+    pub range: u32,
 }
 
 impl Item for GoldDrop {
@@ -55,8 +77,10 @@ impl Item for GoldDrop {
         world: &mut World,
         player_entity: Entity,
         item_entity: Entity,
-    ) -> Result<(), ()> {
-        let mut player_gold = world.get_mut::<Gold>(player_entity).ok_or(())?;
+    ) -> Result<(), ActionError> {
+        let mut player_gold = world
+            .get_mut::<Gold>(player_entity)
+            .ok_or(ActionError::missing::<Gold>())?;
         player_gold.value += self.value;
         despawn_recursive(world, item_entity);
         Ok(())
@@ -93,8 +117,10 @@ impl Item for HealthDrop {
         world: &mut World,
         player_entity: Entity,
         item_entity: Entity,
-    ) -> Result<(), ()> {
-        let mut player_health = world.get_mut::<Health>(player_entity).ok_or(())?;
+    ) -> Result<(), ActionError> {
+        let mut player_health = world
+            .get_mut::<Health>(player_entity)
+            .ok_or(ActionError::missing::<Health>())?;
         player_health.current.min = player_health
             .current
             .max
@@ -128,6 +154,8 @@ impl Item for HealthDrop {
 pub struct Melee {
     pub base_damage: Damage,
     pub current_damage: Damage,
+    // energy a hit takes, heavy weapons swing slower
+    pub attack_cost: u32,
 }
 
 #[derive(Component)]
@@ -144,5 +172,76 @@ pub struct Walk;
 #[derive(Component)]
 pub struct ItemPicker;
 
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum Stairs {
+    Up,
+    Down,
+}
+
+/// a trap that goes off when an actor steps on it
 #[derive(Component)]
-pub struct Portal;
+pub struct Trap {
+    pub kind: TrapKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum TrapKind {
+    Spike,
+    // sends the actor to a random spot on the floor
+    Teleport,
+    // wakes up the npcs around it
+    Alarm,
+    // hurts everything next to the trap
+    PoisonGas,
+}
+
+/// a piece the player has not found yet, it is not drawn until the marker is removed
+#[derive(Component)]
+pub struct Hidden;
+
+/// npcs that heard an alarm chase the player from any distance
+#[derive(Component)]
+pub struct Alerted;
+
+#[derive(Component, Clone)]
+pub struct Key;
+
+impl Item for Key {
+    fn pick_up(
+        &self,
+        world: &mut World,
+        player_entity: Entity,
+        item_entity: Entity,
+    ) -> Result<(), ActionError> {
+        let mut inventory = world
+            .get_mut::<Inventory>(player_entity)
+            .ok_or(ActionError::missing::<Inventory>())?;
+        inventory.items.push(Box::new(self.clone()));
+        despawn_recursive(world, item_entity);
+        Ok(())
+    }
+
+    fn name(&self) -> String {
+        "Key".to_string()
+    }
+
+    fn id(&self) -> u32 {
+        0
+    }
+
+    fn clone_box(&self) -> Box<dyn Item> {
+        Box::new(self.clone())
+    }
+
+    fn as_mut_equippable(&mut self) -> Option<&mut dyn Equippable> {
+        None
+    }
+
+    fn as_equippable(&self) -> Option<&dyn Equippable> {
+        None
+    }
+
+    fn is_key(&self) -> bool {
+        true
+    }
+}
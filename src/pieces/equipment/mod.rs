@@ -1,12 +1,16 @@
 pub mod systems;
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{actions::models::despawn_recursive, player::inventory::Inventory};
+use crate::{
+    actions::{models::despawn_recursive, ActionError, ACTION_COST},
+    player::inventory::Inventory,
+};
 
 use super::components::{Damage, ItemContainer};
 
-#[derive(Component, Clone, PartialEq, Eq)]
+#[derive(Component, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EquipmentSlot {
     Weapon,
     Chest,
@@ -43,6 +47,12 @@ pub trait Equippable: Send + Sync {
     fn damage(&self) -> Option<Damage>;
     fn health(&self) -> Option<u32>;
     fn defense(&self) -> Option<u32>;
+    // how much a wall is worn down per turn of digging, only tools can dig
+    fn dig_power(&self) -> Option<u32>;
+    // energy a hit with the weapon takes, `None` for the normal action cost
+    fn attack_cost(&self) -> Option<u32>;
+    // how many tiles a shot flies, only ranged weapons can shoot
+    fn range(&self) -> Option<u32>;
     fn clone_box(&self) -> Box<dyn Equippable>;
 }
 
@@ -58,12 +68,16 @@ pub trait Item: Send + Sync {
         world: &mut World,
         player_entity: Entity,
         item_entity: Entity,
-    ) -> Result<(), ()>;
+    ) -> Result<(), ActionError>;
     fn id(&self) -> u32;
     fn name(&self) -> String;
     fn clone_box(&self) -> Box<dyn Item>;
     fn as_equippable(&self) -> Option<&dyn Equippable>;
     fn as_mut_equippable(&mut self) -> Option<&mut dyn Equippable>;
+    /// keys are spent to open locked doors
+    fn is_key(&self) -> bool {
+        false
+    }
 }
 
 impl Clone for Box<dyn Item> {
@@ -93,6 +107,15 @@ impl Equippable for Sword {
     fn defense(&self) -> Option<u32> {
         None
     }
+    fn dig_power(&self) -> Option<u32> {
+        None
+    }
+    fn attack_cost(&self) -> Option<u32> {
+        None
+    }
+    fn range(&self) -> Option<u32> {
+        None
+    }
 
     fn clone_box(&self) -> Box<dyn Equippable> {
         Box::new(self.clone())
@@ -105,13 +128,17 @@ impl Item for Sword {
         world: &mut World,
         player_entity: Entity,
         item_entity: Entity,
-    ) -> Result<(), ()> {
+    ) -> Result<(), ActionError> {
         let item = {
-            let item_container_ref = world.get::<ItemContainer>(item_entity).ok_or(())?;
+            let item_container_ref = world
+                .get::<ItemContainer>(item_entity)
+                .ok_or(ActionError::missing::<ItemContainer>())?;
             item_container_ref.item.clone()
         };
 
-        let mut inventory = world.get_mut::<Inventory>(player_entity).ok_or(())?;
+        let mut inventory = world
+            .get_mut::<Inventory>(player_entity)
+            .ok_or(ActionError::missing::<Inventory>())?;
         inventory.items.push(item);
 
         despawn_recursive(world, item_entity);
@@ -160,6 +187,15 @@ impl Equippable for ChestArmor {
     fn defense(&self) -> Option<u32> {
         Some(5)
     }
+    fn dig_power(&self) -> Option<u32> {
+        None
+    }
+    fn attack_cost(&self) -> Option<u32> {
+        None
+    }
+    fn range(&self) -> Option<u32> {
+        None
+    }
 
     fn clone_box(&self) -> Box<dyn Equippable> {
         Box::new(self.clone())
@@ -172,13 +208,17 @@ impl Item for ChestArmor {
         world: &mut World,
         player_entity: Entity,
         item_entity: Entity,
-    ) -> Result<(), ()> {
+    ) -> Result<(), ActionError> {
         let item = {
-            let item_container_ref = world.get::<ItemContainer>(item_entity).ok_or(())?;
+            let item_container_ref = world
+                .get::<ItemContainer>(item_entity)
+                .ok_or(ActionError::missing::<ItemContainer>())?;
             item_container_ref.item.clone()
         };
 
-        let mut inventory = world.get_mut::<Inventory>(player_entity).ok_or(())?;
+        let mut inventory = world
+            .get_mut::<Inventory>(player_entity)
+            .ok_or(ActionError::missing::<Inventory>())?;
         inventory.items.push(item);
 
         despawn_recursive(world, item_entity);
@@ -205,3 +245,165 @@ impl Item for ChestArmor {
         Box::new(self.clone())
     }
 }
+
+#[derive(Component, Clone, Default)]
+pub struct Pickaxe {
+    pub id: u32,
+}
+
+impl Equippable for Pickaxe {
+    fn slot(&self) -> EquipmentSlot {
+        EquipmentSlot::Weapon
+    }
+    fn name(&self) -> String {
+        Item::name(self)
+    }
+    fn damage(&self) -> Option<Damage> {
+        Some(Damage { min: 2, max: 4 })
+    }
+    fn health(&self) -> Option<u32> {
+        None
+    }
+    fn defense(&self) -> Option<u32> {
+        None
+    }
+    fn dig_power(&self) -> Option<u32> {
+        Some(1)
+    }
+    // a pickaxe is heavy, every swing takes one and a half turns
+    fn attack_cost(&self) -> Option<u32> {
+        Some(ACTION_COST * 3 / 2)
+    }
+    fn range(&self) -> Option<u32> {
+        None
+    }
+
+    fn clone_box(&self) -> Box<dyn Equippable> {
+        Box::new(self.clone())
+    }
+}
+
+impl Item for Pickaxe {
+    fn pick_up(
+        &self,
+        world: &mut World,
+        player_entity: Entity,
+        item_entity: Entity,
+    ) -> Result<(), ActionError> {
+        let item = {
+            let item_container_ref = world
+                .get::<ItemContainer>(item_entity)
+                .ok_or(ActionError::missing::<ItemContainer>())?;
+            item_container_ref.item.clone()
+        };
+
+        let mut inventory = world
+            .get_mut::<Inventory>(player_entity)
+            .ok_or(ActionError::missing::<Inventory>())?;
+        inventory.items.push(item);
+
+        despawn_recursive(world, item_entity);
+        Ok(())
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        "Pickaxe".to_string()
+    }
+
+    fn as_equippable(&self) -> Option<&dyn Equippable> {
+        Some(self)
+    }
+
+    fn as_mut_equippable(&mut self) -> Option<&mut dyn Equippable> {
+        Some(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Item> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Component, Clone, Default)]
+pub struct Bow {
+    pub id: u32,
+}
+
+impl Equippable for Bow {
+    fn slot(&self) -> EquipmentSlot {
+        EquipmentSlot::Weapon
+    }
+    fn name(&self) -> String {
+        Item::name(self)
+    }
+    // also what a shot does
+    fn damage(&self) -> Option<Damage> {
+        Some(Damage { min: 3, max: 6 })
+    }
+    fn health(&self) -> Option<u32> {
+        None
+    }
+    fn defense(&self) -> Option<u32> {
+        None
+    }
+    fn dig_power(&self) -> Option<u32> {
+        None
+    }
+    fn attack_cost(&self) -> Option<u32> {
+        None
+    }
+    fn range(&self) -> Option<u32> {
+        Some(8)
+    }
+
+    fn clone_box(&self) -> Box<dyn Equippable> {
+        Box::new(self.clone())
+    }
+}
+
+impl Item for Bow {
+    fn pick_up(
+        &self,
+        world: &mut World,
+        player_entity: Entity,
+        item_entity: Entity,
+    ) -> Result<(), ActionError> {
+        let item = {
+            let item_container_ref = world
+                .get::<ItemContainer>(item_entity)
+                .ok_or(ActionError::missing::<ItemContainer>())?;
+            item_container_ref.item.clone()
+        };
+
+        let mut inventory = world
+            .get_mut::<Inventory>(player_entity)
+            .ok_or(ActionError::missing::<Inventory>())?;
+        inventory.items.push(item);
+
+        despawn_recursive(world, item_entity);
+        Ok(())
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        "Bow".to_string()
+    }
+
+    fn as_equippable(&self) -> Option<&dyn Equippable> {
+        Some(self)
+    }
+
+    fn as_mut_equippable(&mut self) -> Option<&mut dyn Equippable> {
+        Some(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Item> {
+        Box::new(self.clone())
+    }
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
 use crate::{
+    actions::ACTION_COST,
+    messages::{MessageLog, Severity},
     pieces::components::{Health, Melee},
     player::{inventory::Inventory, Player},
 };
@@ -12,6 +14,7 @@ use super::{
 pub fn equip_event_system(
     mut stats_query: Query<(&mut Equipment, &mut Inventory)>,
     mut equip_event: EventReader<EquipItemEvent>,
+    mut log: ResMut<MessageLog>,
 ) {
     for event in equip_event.read() {
         if let Ok((mut equipment, mut inventory)) = stats_query.get_mut(event.entity) {
@@ -29,6 +32,10 @@ pub fn equip_event_system(
             });
 
             if let Some(item) = item_to_equip {
+                log.add(
+                    Severity::Info,
+                    format!("you equip the {}", item.name().to_lowercase()),
+                );
                 equip_item(&mut equipment, item, event.slot.clone());
             }
         }
@@ -63,17 +70,23 @@ fn equip_item(equipment: &mut Equipment, item: Box<dyn Item>, slot: EquipmentSlo
 }
 
 pub fn update_stats(
-    mut stats_query: Query<(&mut Health, &mut Melee, &Equipment), Changed<Equipment>>,
+    mut stats_query: Query<(&mut Health, &mut Melee, &Equipment, Has<Player>), Changed<Equipment>>,
+    mut log: ResMut<MessageLog>,
 ) {
-    for (mut health, mut melee, equipment) in stats_query.iter_mut() {
+    for (mut health, mut melee, equipment, is_player) in stats_query.iter_mut() {
         health.current = health.base;
         melee.current_damage = melee.base_damage;
+        melee.attack_cost = ACTION_COST;
         if let Some(item) = &equipment.weapon {
             if let Some(weapon) = item.as_equippable() {
                 if let Some(damage) = weapon.damage() {
                     melee.current_damage = damage;
                 }
 
+                if let Some(cost) = weapon.attack_cost() {
+                    melee.attack_cost = cost;
+                }
+
                 if let Some(weapon_health) = weapon.health() {
                     health.current.max += weapon_health;
                 }
@@ -90,8 +103,15 @@ pub fn update_stats(
                 }
             }
         }
-        println!("Current max health: {}", health.current.max);
-        println!("Current damage: {}", melee.current_damage.max);
+        if is_player {
+            log.add(
+                Severity::Info,
+                format!(
+                    "max health {}, damage {}-{}",
+                    health.current.max, melee.current_damage.min, melee.current_damage.max
+                ),
+            );
+        }
     }
 }
 
@@ -108,11 +128,15 @@ pub fn unequip_and_return_item(
 pub fn unequip_event_system(
     mut stats_query: Query<(&mut Equipment, &mut Inventory)>,
     mut unequip_event: EventReader<UnequipItemEvent>,
+    mut log: ResMut<MessageLog>,
 ) {
     for event in unequip_event.read() {
         if let Ok((mut equipment, mut inventory)) = stats_query.get_mut(event.entity) {
             if let Some(item) = unequip_and_return_item(&mut equipment, event.slot.clone()) {
-                println!("Unequipped {}", item.name());
+                log.add(
+                    Severity::Info,
+                    format!("you unequip the {}", item.name().to_lowercase()),
+                );
                 inventory.items.push(item);
             }
         }
@@ -1,19 +1,58 @@
 use bevy::prelude::*;
-use components::{Damage, Piece, Range};
+use components::{Damage, Health, Hidden, Melee, Piece, Range, Speed, Trap, TrapKind};
 use equipment::{
     systems::{equip_event_system, player_equip_event_system, unequip_event_system, update_stats},
-    EquipItemEvent, PlayerEquipItemEvent, UnequipItemEvent,
+    EquipItemEvent, Item, PlayerEquipItemEvent, UnequipItemEvent,
 };
-use rand::Rng;
+use rand::seq::SliceRandom;
+use spawn_table::{ItemKind, NpcEntry, SpawnTable, SpawnTableHandle};
+use std::collections::HashSet;
 
 use crate::{
-    board::{components::Position, systems::spawn_map, ValidSpots},
+    actions::ACTION_COST,
+    board::{
+        components::Position,
+        dungeon::room::{PrefabSpawn, RoomKind, RoomRole},
+        systems::spawn_map,
+        theme::LevelThemes,
+        DungeonDepth, DungeonRng, FixedSpawns, KeySpots, LevelCache, LevelRooms, LevelStairs,
+        PrefabSpawns, RoomKinds, ValidSpots,
+    },
     player::{despawn_player, Player},
     states::MainState,
     vectors::Vector2Int,
 };
 pub mod components;
 pub mod equipment;
+pub mod spawn_table;
+
+/// a piece left behind on a floor the player walked away from
+pub enum SavedPiece {
+    Npc {
+        v: Vector2Int,
+        kind: String,
+        base: Range,
+        current: Range,
+        damage: Damage,
+        speed: Speed,
+    },
+    Item {
+        v: Vector2Int,
+        kind: String,
+        item: Box<dyn Item>,
+    },
+    Trap {
+        v: Vector2Int,
+        kind: TrapKind,
+        hidden: bool,
+    },
+}
+
+/// pieces to restore on the current floor
+///
+/// only present while the current floor was brought back from the level cache
+#[derive(Resource)]
+pub struct SavedPieces(pub Vec<SavedPiece>);
 
 pub struct PiecesPlugin;
 
@@ -22,7 +61,19 @@ impl Plugin for PiecesPlugin {
         app.add_event::<EquipItemEvent>()
             .add_event::<UnequipItemEvent>()
             .add_event::<PlayerEquipItemEvent>()
-            .add_systems(OnEnter(MainState::Game), spawn_npcs.after(spawn_map))
+            .init_asset::<spawn_table::SpawnTable>()
+            .init_asset_loader::<spawn_table::SpawnTableLoader>()
+            .add_systems(Startup, load_spawn_table)
+            .add_systems(
+                OnEnter(MainState::Game),
+                (
+                    spawn_npcs.run_if(generated_level),
+                    spawn_fixed_pieces.run_if(resource_exists::<FixedSpawns>),
+                    spawn_keys,
+                    spawn_stairs,
+                )
+                    .after(spawn_map),
+            )
             .add_systems(OnExit(MainState::Game), (despawn_pieces, despawn_player))
             .add_systems(
                 Update,
@@ -40,92 +91,383 @@ impl Plugin for PiecesPlugin {
     }
 }
 
-pub fn spawn_npcs(mut commands: Commands, valid_spots: Res<ValidSpots>) {
-    for _ in 0..10 {
-        spawn_coin(&mut commands, &valid_spots);
-        spawn_test_npc(&mut commands, &valid_spots);
-        spawn_health_drop(&mut commands, &valid_spots)
+fn load_spawn_table(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SpawnTableHandle(
+        asset_server.load(spawn_table::SPAWN_TABLE_PATH),
+    ));
+}
+
+/// run condition, true when the current floor was freshly generated
+pub fn generated_level(
+    fixed_spawns: Option<Res<FixedSpawns>>,
+    saved_pieces: Option<Res<SavedPieces>>,
+) -> bool {
+    fixed_spawns.is_none() && saved_pieces.is_none()
+}
+
+/// fills a generated floor from the spawn table
+///
+/// pieces never share a spot and keep their distance from the player start,
+/// npcs also stay out of the start room.
+/// prefab markers are populated first and don't count against the npc budget
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_npcs(
+    mut commands: Commands,
+    valid_spots: Res<ValidSpots>,
+    key_spots: Res<KeySpots>,
+    stairs: Res<LevelStairs>,
+    room_kinds: Res<RoomKinds>,
+    prefab_spawns: Res<PrefabSpawns>,
+    rooms: Res<LevelRooms>,
+    themes: Res<LevelThemes>,
+    depth: Res<DungeonDepth>,
+    table_handle: Res<SpawnTableHandle>,
+    tables: Res<Assets<SpawnTable>>,
+    mut rng: ResMut<DungeonRng>,
+) {
+    let Some(table) = tables.get(&table_handle.0) else {
+        warn!("Spawn table is not loaded, the floor stays empty");
+        return;
+    };
+    let Some(start) = valid_spots.0.first().copied() else {
+        return;
+    };
+    let rng = &mut rng.0;
+    let depth = depth.current;
+
+    for (v, spawn) in prefab_spawns.0.iter() {
+        match spawn {
+            PrefabSpawn::Npc => {
+                let theme = themes.theme_name(*v);
+                if let Some(npc) = table.choose_npc(depth, RoomKind::Vault, theme, u32::MAX, rng) {
+                    spawn_table_npc(&mut commands, *v, npc);
+                }
+            }
+            PrefabSpawn::Gold(value) => spawn_coin(&mut commands, *v, *value),
+            PrefabSpawn::Health(value) => spawn_health_drop(&mut commands, *v, *value),
+        }
     }
-    spawn_portal(&mut commands, &valid_spots);
+
+    let taken = key_spots
+        .0
+        .iter()
+        .copied()
+        .chain(stairs.up)
+        .chain(stairs.down)
+        .collect::<HashSet<_>>();
+    let mut spots = valid_spots
+        .0
+        .iter()
+        .filter(|v| v.manhattan(start) >= table.min_player_distance && !taken.contains(v))
+        .copied()
+        .collect::<Vec<_>>();
+    spots.shuffle(rng);
+    let mut spots = spots.into_iter();
+    let room_at = |v: Vector2Int| room_kinds.0.get(&v).copied().unwrap_or(RoomKind::Chamber);
+    let start_room = rooms
+        .with_role(RoomRole::Start)
+        .map(|r| r.tiles.iter().copied().collect::<HashSet<_>>())
+        .unwrap_or_default();
+
+    // buy npcs until the encounter budget is spent
+    let mut budget = table.npc_budget.at(depth);
+    // an npc only fits some themes, so look at every theme on the level
+    let min_cost = themes
+        .names()
+        .into_iter()
+        .filter_map(|theme| table.min_npc_cost(depth, theme))
+        .min();
+    while min_cost.is_some_and(|cost| cost <= budget) {
+        let Some(v) = spots.next() else {
+            break;
+        };
+        if start_room.contains(&v) {
+            continue;
+        }
+        let Some(npc) = table.choose_npc(depth, room_at(v), themes.theme_name(v), budget, rng)
+        else {
+            continue;
+        };
+        budget -= npc.cost;
+        spawn_table_npc(&mut commands, v, npc);
+    }
+
+    for _ in 0..table.item_count.at(depth) {
+        let Some(v) = spots.next() else {
+            break;
+        };
+        match table.choose_item(depth, room_at(v), themes.theme_name(v), rng) {
+            Some(ItemKind::Gold(value)) => spawn_coin(&mut commands, v, value),
+            Some(ItemKind::Health(value)) => spawn_health_drop(&mut commands, v, value),
+            None => (),
+        }
+    }
+
+    for _ in 0..table.trap_count.at(depth) {
+        let Some(v) = spots.next() else {
+            break;
+        };
+        if let Some(kind) = table.choose_trap(depth, room_at(v), rng) {
+            spawn_trap(&mut commands, v, kind, true);
+        }
+    }
+}
+
+/// places the pieces of a hand-authored map
+pub fn spawn_fixed_pieces(
+    mut commands: Commands,
+    fixed: Res<FixedSpawns>,
+    depth: Res<DungeonDepth>,
+) {
+    // npcs get tougher the deeper the player goes
+    let npc_health = Range {
+        min: 10 + 2 * depth.current,
+        max: 10 + 2 * depth.current,
+    };
+    fixed
+        .coins
+        .iter()
+        .for_each(|v| spawn_coin(&mut commands, *v, 1));
+    fixed.npcs.iter().for_each(|v| {
+        spawn_npc(
+            &mut commands,
+            *v,
+            "NPC".to_string(),
+            Health {
+                base: npc_health,
+                current: npc_health,
+            },
+            Damage { min: 1, max: 1 },
+            Speed::default(),
+        )
+    });
+    fixed
+        .health
+        .iter()
+        .for_each(|v| spawn_health_drop(&mut commands, *v, 5));
 }
 
-fn spawn_test_npc(commands: &mut Commands, valid_spots: &Res<ValidSpots>) {
-    let rand = rand::thread_rng().gen_range(0..valid_spots.0.len());
+/// a revisited floor gets back exactly what was left on it
+pub fn restore_pieces(mut commands: Commands, saved: Res<SavedPieces>) {
+    for piece in saved.0.iter() {
+        match piece {
+            SavedPiece::Npc {
+                v,
+                kind,
+                base,
+                current,
+                damage,
+                speed,
+            } => spawn_npc(
+                &mut commands,
+                *v,
+                kind.clone(),
+                Health {
+                    base: *base,
+                    current: *current,
+                },
+                *damage,
+                *speed,
+            ),
+            SavedPiece::Item { v, kind, item } => {
+                commands.spawn((
+                    components::ItemContainer { item: item.clone() },
+                    components::Piece { kind: kind.clone() },
+                    Position { v: *v },
+                ));
+            }
+            SavedPiece::Trap { v, kind, hidden } => spawn_trap(&mut commands, *v, *kind, *hidden),
+        }
+    }
+}
+
+pub fn spawn_keys(mut commands: Commands, key_spots: Res<KeySpots>) {
+    key_spots
+        .0
+        .iter()
+        .for_each(|v| spawn_key(&mut commands, *v));
+}
+
+pub fn spawn_stairs(mut commands: Commands, stairs: Res<LevelStairs>) {
+    let up = stairs.up.map(|v| (v, components::Stairs::Up, "Stairs Up"));
+    let down = stairs
+        .down
+        .map(|v| (v, components::Stairs::Down, "Stairs Down"));
+    for (v, stairs, kind) in up.into_iter().chain(down) {
+        commands.spawn((
+            components::Piece {
+                kind: kind.to_string(),
+            },
+            stairs,
+            Position { v },
+        ));
+    }
+}
+
+/// stores the pieces of the floor that is about to be left
+///
+/// runs after `board::systems::save_level`, which creates the cache entry
+#[allow(clippy::type_complexity)]
+pub fn save_pieces(
+    npc_query: Query<
+        (&Position, &Health, &Melee, &Speed, &Piece),
+        (With<components::Walk>, Without<Player>),
+    >,
+    item_query: Query<(&Position, &components::ItemContainer, &Piece)>,
+    trap_query: Query<(&Position, &Trap, Has<Hidden>)>,
+    depth: Res<DungeonDepth>,
+    mut cache: ResMut<LevelCache>,
+) {
+    let Some(level) = cache.0.get_mut(&depth.current) else {
+        return;
+    };
+    level.pieces = npc_query
+        .iter()
+        .map(|(position, health, melee, speed, piece)| SavedPiece::Npc {
+            v: position.v,
+            kind: piece.kind.clone(),
+            base: health.base,
+            current: health.current,
+            damage: melee.base_damage,
+            speed: *speed,
+        })
+        .chain(
+            item_query
+                .iter()
+                .map(|(position, container, piece)| SavedPiece::Item {
+                    v: position.v,
+                    kind: piece.kind.clone(),
+                    item: container.item.clone(),
+                }),
+        )
+        .chain(
+            trap_query
+                .iter()
+                .map(|(position, trap, hidden)| SavedPiece::Trap {
+                    v: position.v,
+                    kind: trap.kind,
+                    hidden,
+                }),
+        )
+        .collect();
+}
+
+fn spawn_npc(
+    commands: &mut Commands,
+    v: Vector2Int,
+    kind: String,
+    health: Health,
+    damage: Damage,
+    speed: Speed,
+) {
     commands.spawn((
         components::Actor::default(),
-        components::Health {
-            base: Range { min: 10, max: 10 },
-            current: Range { min: 10, max: 10 },
-        },
-        components::Piece {
-            kind: "NPC".to_string(),
-        },
-        components::Melee {
-            base_damage: Damage { min: 1, max: 1 },
-            current_damage: Damage { min: 1, max: 1 },
+        speed,
+        components::Energy::default(),
+        health,
+        components::Piece { kind },
+        Melee {
+            base_damage: damage,
+            current_damage: damage,
+            attack_cost: ACTION_COST,
         },
         components::Occupier,
-        Position {
-            v: valid_spots.0[rand],
-        },
+        Position { v },
         components::Walk,
     ));
 }
 
-fn spawn_coin(commands: &mut Commands, valid_spots: &Res<ValidSpots>) {
-    let rand = rand::thread_rng().gen_range(0..valid_spots.0.len());
+fn spawn_table_npc(commands: &mut Commands, v: Vector2Int, npc: &NpcEntry) {
+    let health = Range {
+        min: npc.health,
+        max: npc.health,
+    };
+    let damage = Damage {
+        min: npc.damage,
+        max: npc.damage,
+    };
+    spawn_npc(
+        commands,
+        v,
+        npc.kind.clone(),
+        Health {
+            base: health,
+            current: health,
+        },
+        damage,
+        Speed(npc.speed),
+    );
+}
+
+fn spawn_coin(commands: &mut Commands, v: Vector2Int, value: u32) {
     commands.spawn((
         components::ItemContainer {
-            item: Box::new(components::GoldDrop { value: 1 }),
+            item: Box::new(components::GoldDrop { value }),
         },
         components::Piece {
             kind: "Coin".to_string(),
         },
-        Position {
-            v: valid_spots.0[rand],
-        },
+        Position { v },
     ));
 }
 
-fn spawn_health_drop(commands: &mut Commands, valid_spots: &Res<ValidSpots>) {
-    let rand = rand::thread_rng().gen_range(0..valid_spots.0.len());
+fn spawn_health_drop(commands: &mut Commands, v: Vector2Int, value: u32) {
     commands.spawn((
         components::ItemContainer {
-            item: Box::new(components::HealthDrop { value: 5 }),
+            item: Box::new(components::HealthDrop { value }),
         },
         components::Piece {
             kind: "Health".to_string(),
         },
-        Position {
-            v: valid_spots.0[rand],
-        },
+        Position { v },
     ));
 }
 
-fn spawn_portal(commands: &mut Commands, valid_spots: &Res<ValidSpots>) {
-    let rand = rand::thread_rng().gen_range(0..valid_spots.0.len());
+fn spawn_key(commands: &mut Commands, v: Vector2Int) {
     commands.spawn((
+        components::ItemContainer {
+            item: Box::new(components::Key),
+        },
         components::Piece {
-            kind: "Portal".to_string(),
+            kind: "Key".to_string(),
         },
-        components::Portal,
-        Position {
-            v: valid_spots.0[rand],
+        Position { v },
+    ));
+}
+
+fn spawn_trap(commands: &mut Commands, v: Vector2Int, kind: TrapKind, hidden: bool) {
+    let mut trap = commands.spawn((
+        Trap { kind },
+        components::Piece {
+            kind: "Trap".to_string(),
         },
+        Position { v },
     ));
+    if hidden {
+        trap.insert(Hidden);
+    }
 }
 
+/// spawns a shot on the shooter's tile, it flies towards `destination` on its own turn
+///
+/// takes the world since shots are fired from within actions
 pub fn spawn_projectile(
-    commands: &mut Commands,
+    world: &mut World,
     source: Vector2Int,
     destination: Vector2Int,
+    damage: Damage,
+    range: u32,
 ) -> Entity {
-    commands
+    world
         .spawn((
             components::Actor::default(),
+            Speed::default(),
+            // projectiles fly off right away
+            components::Energy(ACTION_COST as i32),
             components::Projectile {
                 destination,
-                damage: Damage { min: 5, max: 5 },
+                damage,
+                range,
             },
             components::Piece {
                 kind: "Coin".to_string(),
@@ -0,0 +1,344 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use rand::{distributions::uniform::SampleUniform, rngs::StdRng, Rng};
+use serde::Deserialize;
+use std::iter::Sum;
+use std::ops::SubAssign;
+
+use crate::board::dungeon::room::RoomKind;
+
+use super::components::{TrapKind, NORMAL_SPEED};
+
+pub const SPAWN_TABLE_PATH: &str = "spawn_table.spawns.ron";
+
+/// weighted lists of what can spawn on a generated floor
+///
+/// npcs are bought from an encounter budget that grows with depth,
+/// so deeper floors get more and tougher npcs
+#[derive(Asset, TypePath, Deserialize)]
+pub struct SpawnTable {
+    pub npc_budget: Scaling,
+    pub item_count: Scaling,
+    // nothing spawns closer than this to the player start
+    pub min_player_distance: i32,
+    pub npcs: Vec<NpcEntry>,
+    pub items: Vec<ItemEntry>,
+    // traps go on the spots left over after npcs and items, they start out hidden
+    #[serde(default)]
+    pub trap_count: Scaling,
+    #[serde(default)]
+    pub traps: Vec<TrapEntry>,
+}
+
+/// a value of `base + per_depth * depth`
+#[derive(Clone, Copy, Default, Deserialize)]
+pub struct Scaling {
+    pub base: u32,
+    #[serde(default)]
+    pub per_depth: u32,
+}
+
+impl Scaling {
+    pub fn at(&self, depth: u32) -> u32 {
+        self.base + self.per_depth * depth
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NpcEntry {
+    pub kind: String,
+    pub health: u32,
+    pub damage: u32,
+    // share of the encounter budget
+    pub cost: u32,
+    pub weight: u32,
+    // energy gained per turn, twice the normal speed acts twice per player turn
+    #[serde(default = "normal_speed")]
+    pub speed: u32,
+    #[serde(default)]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    // an empty list allows every room kind
+    #[serde(default)]
+    pub rooms: Vec<RoomKind>,
+    // names of the level themes the entry spawns in, an empty list allows every theme
+    #[serde(default)]
+    pub themes: Vec<String>,
+}
+
+fn normal_speed() -> u32 {
+    NORMAL_SPEED
+}
+
+#[derive(Deserialize)]
+pub struct ItemEntry {
+    pub item: ItemKind,
+    pub weight: u32,
+    #[serde(default)]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    #[serde(default)]
+    pub rooms: Vec<RoomKind>,
+    #[serde(default)]
+    pub themes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TrapEntry {
+    pub kind: TrapKind,
+    pub weight: u32,
+    #[serde(default)]
+    pub min_depth: u32,
+    #[serde(default)]
+    pub max_depth: Option<u32>,
+    #[serde(default)]
+    pub rooms: Vec<RoomKind>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum ItemKind {
+    Gold(u32),
+    Health(u32),
+}
+
+impl NpcEntry {
+    fn fits(&self, depth: u32, room: RoomKind, theme: Option<&str>) -> bool {
+        fits(self.min_depth, self.max_depth, &self.rooms, depth, room)
+            && fits_theme(&self.themes, theme)
+    }
+}
+
+impl ItemEntry {
+    fn fits(&self, depth: u32, room: RoomKind, theme: Option<&str>) -> bool {
+        fits(self.min_depth, self.max_depth, &self.rooms, depth, room)
+            && fits_theme(&self.themes, theme)
+    }
+}
+
+impl TrapEntry {
+    fn fits(&self, depth: u32, room: RoomKind) -> bool {
+        fits(self.min_depth, self.max_depth, &self.rooms, depth, room)
+    }
+}
+
+impl SpawnTable {
+    /// picks an npc for the given spot that costs no more than `budget`
+    pub fn choose_npc(
+        &self,
+        depth: u32,
+        room: RoomKind,
+        theme: Option<&str>,
+        budget: u32,
+        rng: &mut StdRng,
+    ) -> Option<&NpcEntry> {
+        let candidates = self
+            .npcs
+            .iter()
+            .filter(|e| e.cost <= budget && e.fits(depth, room, theme))
+            .map(|e| (e, e.weight));
+        choose_weighted(candidates, rng)
+    }
+
+    pub fn choose_item(
+        &self,
+        depth: u32,
+        room: RoomKind,
+        theme: Option<&str>,
+        rng: &mut StdRng,
+    ) -> Option<ItemKind> {
+        let candidates = self
+            .items
+            .iter()
+            .filter(|e| e.fits(depth, room, theme))
+            .map(|e| (e, e.weight));
+        choose_weighted(candidates, rng).map(|e| e.item)
+    }
+
+    pub fn choose_trap(&self, depth: u32, room: RoomKind, rng: &mut StdRng) -> Option<TrapKind> {
+        let candidates = self
+            .traps
+            .iter()
+            .filter(|e| e.fits(depth, room))
+            .map(|e| (e, e.weight));
+        choose_weighted(candidates, rng).map(|e| e.kind)
+    }
+
+    /// rejects entries the spawner can't work with, so a broken table fails to load
+    pub fn validate(&self) -> Result<(), String> {
+        for npc in self.npcs.iter() {
+            if npc.cost == 0 {
+                return Err(format!("npc {} costs nothing", npc.kind));
+            }
+            if npc.health == 0 {
+                return Err(format!("npc {} has no health", npc.kind));
+            }
+            if npc.speed == 0 {
+                return Err(format!("npc {} never acts", npc.kind));
+            }
+        }
+        let depths = self
+            .npcs
+            .iter()
+            .map(|e| (e.kind.clone(), e.min_depth, e.max_depth))
+            .chain(
+                self.items
+                    .iter()
+                    .map(|e| (format!("{:?}", e.item), e.min_depth, e.max_depth)),
+            )
+            .chain(
+                self.traps
+                    .iter()
+                    .map(|e| (format!("{:?}", e.kind), e.min_depth, e.max_depth)),
+            );
+        for (name, min_depth, max_depth) in depths {
+            if max_depth.is_some_and(|max| max < min_depth) {
+                return Err(format!("{} can never spawn, max_depth < min_depth", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// cheapest npc that can spawn at this depth and theme at all,
+    /// used to know when the budget is spent
+    pub fn min_npc_cost(&self, depth: u32, theme: Option<&str>) -> Option<u32> {
+        self.npcs
+            .iter()
+            .filter(|e| in_depth(e.min_depth, e.max_depth, depth) && fits_theme(&e.themes, theme))
+            .map(|e| e.cost)
+            .min()
+    }
+}
+
+fn fits(
+    min_depth: u32,
+    max_depth: Option<u32>,
+    rooms: &[RoomKind],
+    depth: u32,
+    room: RoomKind,
+) -> bool {
+    in_depth(min_depth, max_depth, depth) && (rooms.is_empty() || rooms.contains(&room))
+}
+
+/// unthemed levels allow every entry
+fn fits_theme(themes: &[String], theme: Option<&str>) -> bool {
+    match theme {
+        Some(theme) => themes.is_empty() || themes.iter().any(|t| t == theme),
+        None => true,
+    }
+}
+
+pub fn in_depth(min_depth: u32, max_depth: Option<u32>, depth: u32) -> bool {
+    depth >= min_depth && max_depth.is_none_or(|max| depth <= max)
+}
+
+/// picks one of the candidates by weight, entries with a weight of 0 are never picked
+///
+/// weights can be integers or chances
+pub fn choose_weighted<'a, T, W>(
+    candidates: impl Iterator<Item = (&'a T, W)>,
+    rng: &mut StdRng,
+) -> Option<&'a T>
+where
+    W: SampleUniform + PartialOrd + Default + Copy + Sum + SubAssign,
+{
+    let candidates = candidates
+        .filter(|(_, w)| *w > W::default())
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return None;
+    }
+    let total = candidates.iter().map(|(_, w)| *w).sum::<W>();
+    let mut roll = rng.gen_range(W::default()..total);
+    for (entry, weight) in candidates.iter() {
+        if roll < *weight {
+            return Some(entry);
+        }
+        roll -= *weight;
+    }
+    // only reached when float weights don't add up exactly
+    candidates.last().map(|(entry, _)| *entry)
+}
+
+#[derive(Resource)]
+pub struct SpawnTableHandle(pub Handle<SpawnTable>);
+
+#[derive(Default)]
+pub struct SpawnTableLoader;
+
+impl AssetLoader for SpawnTableLoader {
+    type Asset = SpawnTable;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let table = ron::de::from_bytes::<SpawnTable>(&bytes)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            table
+                .validate()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            Ok(table)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["spawns.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(npcs: &str) -> SpawnTable {
+        let ron = format!(
+            "(npc_budget: (base: 4), item_count: (base: 0), min_player_distance: 0, npcs: [{}], items: [])",
+            npcs
+        );
+        ron::de::from_str(&ron).unwrap()
+    }
+
+    #[test]
+    fn shipped_table_is_valid() {
+        let table: SpawnTable =
+            ron::de::from_str(include_str!("../../assets/spawn_table.spawns.ron")).unwrap();
+        assert_eq!(table.validate(), Ok(()));
+    }
+
+    #[test]
+    fn free_npc_fails_validation() {
+        let table = table(r#"(kind: "Rat", health: 4, damage: 1, cost: 0, weight: 1)"#);
+        assert_eq!(table.validate(), Err("npc Rat costs nothing".to_string()));
+    }
+
+    #[test]
+    fn inverted_depths_fail_validation() {
+        let table = table(
+            r#"(kind: "Rat", health: 4, damage: 1, cost: 1, weight: 1, min_depth: 3, max_depth: Some(2))"#,
+        );
+        assert!(table.validate().is_err());
+    }
+
+    #[test]
+    fn min_npc_cost_skips_other_themes() {
+        let table = table(
+            r#"(kind: "Rat", health: 4, damage: 1, cost: 1, weight: 1, themes: ["Cave"]),
+            (kind: "Orc", health: 9, damage: 2, cost: 3, weight: 1)"#,
+        );
+        assert_eq!(table.min_npc_cost(0, Some("Cave")), Some(1));
+        assert_eq!(table.min_npc_cost(0, Some("Crypt")), Some(3));
+        assert_eq!(table.min_npc_cost(0, None), Some(1));
+    }
+}
@@ -4,11 +4,14 @@ use bevy::prelude::*;
 use inventory::Inventory;
 
 use crate::{
+    actions::ACTION_COST,
     board::{components::Position, systems::spawn_map, ValidSpots},
     graphics::TILE_SIZE,
     pieces::{
-        components::{Actor, Damage, Gold, Health, ItemPicker, Melee, Occupier, Piece, Range},
-        equipment::{ChestArmor, Equipment, Sword},
+        components::{
+            Actor, Damage, Energy, Gold, Health, ItemPicker, Melee, Occupier, Piece, Range, Speed,
+        },
+        equipment::{Bow, ChestArmor, Equipment, Pickaxe, Sword},
     },
     states::MainState,
 };
@@ -27,6 +30,9 @@ pub struct Player;
 pub fn spawn_player(mut commands: Commands, valid_spots: Res<ValidSpots>) {
     commands.spawn((
         Actor::default(),
+        Speed::default(),
+        // the player makes the first move
+        Energy(ACTION_COST as i32),
         Player,
         Occupier,
         ItemPicker,
@@ -38,9 +44,15 @@ pub fn spawn_player(mut commands: Commands, valid_spots: Res<ValidSpots>) {
         Melee {
             base_damage: Damage { min: 5, max: 5 },
             current_damage: Damage { min: 5, max: 5 },
+            attack_cost: ACTION_COST,
         },
         Inventory {
-            items: vec![Box::new(Sword { id: 5 }), Box::new(ChestArmor { id: 6 })],
+            items: vec![
+                Box::new(Sword { id: 5 }),
+                Box::new(ChestArmor { id: 6 }),
+                Box::new(Pickaxe { id: 7 }),
+                Box::new(Bow { id: 8 }),
+            ],
         },
         Piece {
             kind: "Player".to_string(),
@@ -0,0 +1,428 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+use crate::{
+    board::{components::Door, components::Position, DungeonDepth, DungeonSeed},
+    input::{apply_player_command, PlayerCommand, PlayerCommandEvent, ReadInputSet},
+    messages::{MessageLog, Severity},
+    options::LaunchOptions,
+    pieces::{
+        components::{Energy, Gold, Health, Piece},
+        equipment::{EquipmentSlot, PlayerEquipItemEvent, UnequipItemEvent},
+    },
+    player::Player,
+    states::{GameState, MainState},
+};
+
+// FNV-1a, spelled out so checksums stay the same across Rust versions
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, setup)
+            .add_systems(
+                Update,
+                (record_equipment, record_commands)
+                    .chain()
+                    .after(ReadInputSet)
+                    .before(apply_player_command)
+                    .run_if(resource_exists::<Recorder>),
+            )
+            .add_systems(
+                Update,
+                play_back
+                    .before(apply_player_command)
+                    .run_if(resource_exists::<Replay>)
+                    .run_if(in_state(GameState::PlayerInput)),
+            )
+            .add_systems(OnEnter(MainState::GameOver), stop);
+    }
+}
+
+/// everything needed to play a run again, written as ron
+///
+/// a run is reproduced from its seed and hand-authored maps, after that only the player's
+/// decisions are stored, everything else follows from the seeded rngs
+///
+/// the file holds the seed and maps on its first line, followed by a line per turn
+#[derive(Default, Serialize, Deserialize)]
+pub struct Recording {
+    // both the dungeon and the gameplay rng are seeded from it
+    pub seed: u64,
+    #[serde(default)]
+    pub maps: Vec<(u32, String)>,
+    #[serde(default, skip_serializing)]
+    pub turns: Vec<RecordedTurn>,
+}
+
+impl Recording {
+    /// reads a file written by `Recorder`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header = lines.next().ok_or("the file is empty")?;
+        let mut recording = ron::de::from_str::<Recording>(header).map_err(|e| e.to_string())?;
+        for (i, line) in lines.enumerate() {
+            let turn = ron::de::from_str(line).map_err(|e| format!("turn {}: {}", i, e))?;
+            recording.turns.push(turn);
+        }
+        Ok(recording)
+    }
+
+    /// the lines for the turns from `start` on, with the header in front when starting from 0
+    fn lines(&self, start: usize) -> Result<String, String> {
+        let mut text = String::new();
+        if start == 0 {
+            text += &ron::ser::to_string(self).map_err(|e| e.to_string())?;
+            text.push('\n');
+        }
+        for turn in self.turns.iter().skip(start) {
+            text += &ron::ser::to_string(turn).map_err(|e| e.to_string())?;
+            text.push('\n');
+        }
+        Ok(text)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecordedTurn {
+    // equipment the player changed since the last turn, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equipment: Vec<EquipmentChange>,
+    pub command: PlayerCommand,
+    // of the world right before the command was given, a replay that disagrees has desynced
+    pub checksum: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum EquipmentChange {
+    Equip { slot: EquipmentSlot, id: u32 },
+    Unequip { slot: EquipmentSlot },
+}
+
+/// writes the player's turns to `path`, each turn is appended as it is played
+/// so a crash still leaves a usable replay
+#[derive(Resource)]
+pub struct Recorder {
+    pub path: String,
+    pub recording: Recording,
+    pending_equipment: Vec<EquipmentChange>,
+    // turns already in the file
+    written: usize,
+}
+
+/// a recording being played back, its presence turns off keyboard input
+#[derive(Resource)]
+pub struct Replay {
+    pub recording: Recording,
+    // index of the turn that is played next
+    pub next: usize,
+    // turns whose checksum did not match the recording
+    pub desyncs: Vec<usize>,
+    equipment_sent: bool,
+}
+
+/// a checksum of the game state turns are compared by
+///
+/// covers the depth, the pieces with their health and energy, the doors and the player's gold,
+/// which is where a diverging run shows up first
+#[derive(SystemParam)]
+pub struct WorldChecksum<'w, 's> {
+    depth: Res<'w, DungeonDepth>,
+    piece_query: Query<
+        'w,
+        's,
+        (
+            &'static Piece,
+            &'static Position,
+            Option<&'static Health>,
+            Option<&'static Energy>,
+        ),
+    >,
+    door_query: Query<'w, 's, (&'static Door, &'static Position)>,
+    gold_query: Query<'w, 's, &'static Gold, With<Player>>,
+}
+
+impl WorldChecksum<'_, '_> {
+    pub fn compute(&self) -> u64 {
+        // sorted so the result doesn't depend on query order
+        let mut pieces = self
+            .piece_query
+            .iter()
+            .map(|(piece, position, health, energy)| {
+                (
+                    piece.kind.as_str(),
+                    position.v,
+                    health.map(|h| h.current.min),
+                    energy.map(|e| e.0),
+                )
+            })
+            .collect::<Vec<_>>();
+        pieces.sort();
+        let mut doors = self
+            .door_query
+            .iter()
+            .map(|(door, position)| (position.v, door.open, door.locked))
+            .collect::<Vec<_>>();
+        doors.sort();
+
+        let mut hash = fnv(FNV_OFFSET, &self.depth.current.to_le_bytes());
+        for (kind, v, health, energy) in pieces {
+            hash = fnv(hash, kind.as_bytes());
+            hash = fnv(hash, &v.x.to_le_bytes());
+            hash = fnv(hash, &v.y.to_le_bytes());
+            hash = fnv(hash, &health.unwrap_or(u32::MAX).to_le_bytes());
+            hash = fnv(hash, &energy.unwrap_or(i32::MIN).to_le_bytes());
+        }
+        for (v, open, locked) in doors {
+            hash = fnv(hash, &v.x.to_le_bytes());
+            hash = fnv(hash, &v.y.to_le_bytes());
+            hash = fnv(hash, &[open as u8, locked as u8]);
+        }
+        for gold in self.gold_query.iter() {
+            hash = fnv(hash, &gold.value.to_le_bytes());
+        }
+        hash
+    }
+}
+
+fn fnv(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |h, b| (h ^ *b as u64).wrapping_mul(FNV_PRIME))
+}
+
+fn setup(
+    mut commands: Commands,
+    mut options: ResMut<LaunchOptions>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    if let Some(path) = &options.record {
+        commands.insert_resource(Recorder {
+            path: path.clone(),
+            recording: Recording::default(),
+            pending_equipment: Vec::new(),
+            written: 0,
+        });
+    }
+    let Some(path) = &options.replay else {
+        return;
+    };
+    let recording = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| Recording::parse(&s));
+    match recording {
+        Ok(recording) => {
+            info!("Replaying {} turns from {}", recording.turns.len(), path);
+            // the run starts the way the recorded one did
+            options.seed = Some(recording.seed);
+            options.maps = recording.maps.clone();
+            commands.insert_resource(Replay {
+                recording,
+                next: 0,
+                desyncs: Vec::new(),
+                equipment_sent: false,
+            });
+            if let Some(speed) = options.replay_speed {
+                time.set_relative_speed(speed);
+            }
+        }
+        Err(e) => warn!("Could not load replay {}: {}", path, e),
+    }
+}
+
+fn record_equipment(
+    mut recorder: ResMut<Recorder>,
+    player_query: Query<Entity, With<Player>>,
+    mut ev_equip: EventReader<PlayerEquipItemEvent>,
+    mut ev_unequip: EventReader<UnequipItemEvent>,
+) {
+    for ev in ev_equip.read() {
+        recorder.pending_equipment.push(EquipmentChange::Equip {
+            slot: ev.slot.clone(),
+            id: ev.id,
+        });
+    }
+    for ev in ev_unequip.read() {
+        if player_query.contains(ev.entity) {
+            recorder.pending_equipment.push(EquipmentChange::Unequip {
+                slot: ev.slot.clone(),
+            });
+        }
+    }
+}
+
+fn record_commands(
+    mut recorder: ResMut<Recorder>,
+    mut ev_command: EventReader<PlayerCommandEvent>,
+    checksum: WorldChecksum,
+    seed: Option<Res<DungeonSeed>>,
+    options: Res<LaunchOptions>,
+) {
+    if ev_command.is_empty() {
+        return;
+    }
+    let checksum = checksum.compute();
+    for PlayerCommandEvent(command) in ev_command.read() {
+        let equipment = std::mem::take(&mut recorder.pending_equipment);
+        recorder.recording.turns.push(RecordedTurn {
+            equipment,
+            command: *command,
+            checksum,
+        });
+    }
+    recorder.recording.seed = seed.map(|s| s.0).unwrap_or_default();
+    recorder.recording.maps = options.maps.clone();
+    write_recording(&mut recorder);
+}
+
+/// appends the turns that are not in the file yet, the file is started over with the first one
+fn write_recording(recorder: &mut Recorder) {
+    if recorder.written == recorder.recording.turns.len() {
+        return;
+    }
+    let result = recorder.recording.lines(recorder.written).and_then(|text| {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(recorder.written > 0)
+            .truncate(recorder.written == 0)
+            .open(&recorder.path)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|e| e.to_string())
+    });
+    match result {
+        Ok(()) => recorder.written = recorder.recording.turns.len(),
+        Err(e) => warn!("Could not write replay {}: {}", recorder.path, e),
+    }
+}
+
+/// feeds the next recorded turn to the player, one turn per player input phase
+///
+/// equipment changes go out a frame before the command, as they would have from the menu
+#[allow(clippy::too_many_arguments)]
+fn play_back(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    checksum: WorldChecksum,
+    player_query: Query<Entity, With<Player>>,
+    mut ev_command: EventWriter<PlayerCommandEvent>,
+    mut ev_equip: EventWriter<PlayerEquipItemEvent>,
+    mut ev_unequip: EventWriter<UnequipItemEvent>,
+    mut log: ResMut<MessageLog>,
+) {
+    let Some(turn) = replay.recording.turns.get(replay.next).cloned() else {
+        finish(&mut commands, &replay, &mut log);
+        return;
+    };
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+    if !replay.equipment_sent && !turn.equipment.is_empty() {
+        for change in turn.equipment {
+            match change {
+                EquipmentChange::Equip { slot, id } => {
+                    ev_equip.send(PlayerEquipItemEvent { slot, id });
+                }
+                EquipmentChange::Unequip { slot } => {
+                    ev_unequip.send(UnequipItemEvent {
+                        entity: player,
+                        slot,
+                    });
+                }
+            }
+        }
+        replay.equipment_sent = true;
+        return;
+    }
+
+    if checksum.compute() != turn.checksum {
+        let turn_index = replay.next;
+        warn!("Replay desynced on turn {}", turn_index);
+        if replay.desyncs.is_empty() {
+            log.add(
+                Severity::Warning,
+                format!("the replay desynced on turn {}", turn_index),
+            );
+        }
+        replay.desyncs.push(turn_index);
+    }
+    ev_command.send(PlayerCommandEvent(turn.command));
+    replay.next += 1;
+    replay.equipment_sent = false;
+}
+
+/// hands control back to the keyboard
+fn finish(commands: &mut Commands, replay: &Replay, log: &mut MessageLog) {
+    let text = match replay.desyncs.len() {
+        0 => format!("replay finished after {} turns", replay.next),
+        n => format!(
+            "replay finished after {} turns, {} of them desynced",
+            replay.next, n
+        ),
+    };
+    info!("{}", text);
+    log.add(Severity::Info, text);
+    commands.remove_resource::<Replay>();
+}
+
+/// the run is over, a new game is not part of the recording
+fn stop(
+    mut commands: Commands,
+    recorder: Option<ResMut<Recorder>>,
+    replay: Option<Res<Replay>>,
+    mut log: ResMut<MessageLog>,
+) {
+    if let Some(mut recorder) = recorder {
+        write_recording(&mut recorder);
+        commands.remove_resource::<Recorder>();
+    }
+    if let Some(replay) = replay {
+        finish(&mut commands, &replay, &mut log);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::Vector2Int;
+
+    #[test]
+    fn appended_turns_read_back() {
+        let mut recording = Recording {
+            seed: 1234,
+            maps: vec![(0, "map.txt".to_string())],
+            turns: Vec::new(),
+        };
+        let turn = |command| RecordedTurn {
+            equipment: Vec::new(),
+            command,
+            checksum: 7,
+        };
+        recording
+            .turns
+            .push(turn(PlayerCommand::Step(Vector2Int::UP)));
+        let mut text = recording.lines(0).unwrap();
+        recording.turns.push(turn(PlayerCommand::Search));
+        recording
+            .turns
+            .push(turn(PlayerCommand::Shoot(Vector2Int::new(3, -2))));
+        text += &recording.lines(1).unwrap();
+
+        let read = Recording::parse(&text).unwrap();
+        assert_eq!(read.seed, 1234);
+        assert_eq!(read.maps, recording.maps);
+        let commands = read.turns.iter().map(|t| t.command).collect::<Vec<_>>();
+        assert_eq!(
+            commands,
+            [
+                PlayerCommand::Step(Vector2Int::UP),
+                PlayerCommand::Search,
+                PlayerCommand::Shoot(Vector2Int::new(3, -2))
+            ]
+        );
+    }
+}
@@ -16,13 +16,31 @@ pub struct UiHealth;
 #[derive(Component)]
 pub struct UiGold;
 
+#[derive(Component)]
+pub struct UiSeed;
+
+#[derive(Component)]
+pub struct UiDepth;
+
+/// the message log panel, `scroll` counts the lines it is scrolled back from the newest
+#[derive(Component, Default)]
+pub struct UiLog {
+    pub scroll: usize,
+}
+
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(PreStartup, setup)
             .add_systems(OnEnter(MainState::Game), systems::spawn_ui)
             .add_systems(
                 Update,
-                (systems::update_ui_gold, systems::update_ui_health)
+                (
+                    systems::update_ui_gold,
+                    systems::update_ui_health,
+                    systems::update_ui_seed,
+                    systems::update_ui_depth,
+                    systems::update_ui_log,
+                )
                     .run_if(in_state(MainState::Game)),
             );
     }
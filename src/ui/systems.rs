@@ -1,17 +1,26 @@
 use bevy::prelude::*;
 
 use crate::{
+    board::{DungeonDepth, DungeonSeed},
+    messages::MessageLog,
     pieces::components::{Gold, Health, Range},
     player::Player,
 };
 
-use super::{UiFont, UiGold, UiHealth};
+use super::{UiDepth, UiFont, UiGold, UiHealth, UiLog, UiSeed};
+
+// messages the log panel shows at once
+const LOG_LINES: usize = 8;
 
 pub fn spawn_ui(mut commands: Commands, font: Res<UiFont>) {
     let health = spawn_health_ui(&mut commands, &font);
 
     let gold = spawn_gold_ui(&mut commands, &font);
 
+    let depth = spawn_depth_ui(&mut commands, &font);
+
+    let seed = spawn_seed_ui(&mut commands, &font);
+
     let node_bundle = NodeBundle {
         style: Style {
             width: Val::Percent(100.),
@@ -25,7 +34,40 @@ pub fn spawn_ui(mut commands: Commands, font: Res<UiFont>) {
         ..default()
     };
 
-    commands.spawn(node_bundle).push_children(&[health, gold]);
+    commands
+        .spawn(node_bundle)
+        .push_children(&[health, gold, depth, seed]);
+
+    spawn_log_ui(&mut commands, &font);
+}
+
+fn spawn_log_ui(commands: &mut Commands, font: &Res<UiFont>) {
+    let log = commands
+        .spawn(TextBundle::from_section(
+            "",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 18.0,
+                color: Color::rgb(0.7, 0.7, 0.7),
+            },
+        ))
+        .insert(UiLog::default())
+        .id();
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(14.),
+                left: Val::Px(14.),
+                width: Val::Percent(40.),
+                padding: UiRect::all(Val::Px(6.)),
+                ..default()
+            },
+            background_color: Color::rgba(0.12, 0.11, 0.09, 0.6).into(),
+            ..default()
+        })
+        .add_child(log);
 }
 
 fn spawn_health_ui(commands: &mut Commands, font: &Res<UiFont>) -> Entity {
@@ -56,6 +98,34 @@ fn spawn_gold_ui(commands: &mut Commands, font: &Res<UiFont>) -> Entity {
         .id()
 }
 
+fn spawn_depth_ui(commands: &mut Commands, font: &Res<UiFont>) -> Entity {
+    commands
+        .spawn(TextBundle::from_section(
+            "Depth: ",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 30.0,
+                color: Color::rgb(0.7, 0.7, 0.7),
+            },
+        ))
+        .insert(UiDepth)
+        .id()
+}
+
+fn spawn_seed_ui(commands: &mut Commands, font: &Res<UiFont>) -> Entity {
+    commands
+        .spawn(TextBundle::from_section(
+            "Seed: ",
+            TextStyle {
+                font: font.0.clone(),
+                font_size: 20.0,
+                color: Color::rgb(0.5, 0.5, 0.5),
+            },
+        ))
+        .insert(UiSeed)
+        .id()
+}
+
 pub fn update_ui_health(
     mut text_query: Query<&mut Text, With<UiHealth>>,
     health_query: Query<&Health, With<Player>>,
@@ -78,3 +148,58 @@ pub fn update_ui_gold(
         text.sections[0].value = format!("Gold: {}", gold.value);
     }
 }
+
+pub fn update_ui_seed(
+    mut text_query: Query<&mut Text, With<UiSeed>>,
+    seed: Option<Res<DungeonSeed>>,
+) {
+    let Some(seed) = seed else {
+        return;
+    };
+    for mut text in &mut text_query {
+        text.sections[0].value = format!("Seed: {}", seed.0);
+    }
+}
+
+pub fn update_ui_depth(mut text_query: Query<&mut Text, With<UiDepth>>, depth: Res<DungeonDepth>) {
+    for mut text in &mut text_query {
+        text.sections[0].value = format!("Depth: {}", depth.current);
+    }
+}
+
+/// shows the newest messages, page up and page down scroll through older ones
+pub fn update_ui_log(
+    mut text_query: Query<(&mut Text, &mut UiLog)>,
+    log: Res<MessageLog>,
+    font: Res<UiFont>,
+    keys: Res<ButtonInput<KeyCode>>,
+) {
+    let max_scroll = log.len().saturating_sub(LOG_LINES);
+    for (mut text, mut ui_log) in &mut text_query {
+        let mut scroll = ui_log.scroll.min(max_scroll);
+        if keys.just_pressed(KeyCode::PageUp) {
+            scroll = (scroll + LOG_LINES).min(max_scroll);
+        }
+        if keys.just_pressed(KeyCode::PageDown) {
+            scroll = scroll.saturating_sub(LOG_LINES);
+        }
+        if scroll == ui_log.scroll && !log.is_changed() {
+            continue;
+        }
+        ui_log.scroll = scroll;
+
+        text.sections = log
+            .page(scroll, LOG_LINES)
+            .map(|message| {
+                TextSection::new(
+                    format!("{}\n", message.text),
+                    TextStyle {
+                        font: font.0.clone(),
+                        font_size: 18.0,
+                        color: message.severity.color(),
+                    },
+                )
+            })
+            .collect();
+    }
+}
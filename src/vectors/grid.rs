@@ -0,0 +1,123 @@
+use std::ops::{Index, IndexMut};
+
+use super::{Vector2Int, ORTHO_DIRECTIONS};
+
+/// a dense, rectangular map of values indexed by board positions
+///
+/// the bounds are inclusive and don't have to start at the origin
+#[derive(Clone, Debug)]
+pub struct Grid<T> {
+    min: Vector2Int,
+    size: Vector2Int,
+    cells: Vec<T>,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Grid {
+            min: Vector2Int::default(),
+            size: Vector2Int::default(),
+            cells: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    /// creates a grid covering `min..=max` with every cell set to `value`
+    pub fn new(min: Vector2Int, max: Vector2Int, value: T) -> Self {
+        let size = Vector2Int::new((max.x - min.x + 1).max(0), (max.y - min.y + 1).max(0));
+        Grid {
+            min,
+            size,
+            cells: vec![value; (size.x * size.y) as usize],
+        }
+    }
+
+    /// creates the smallest grid that covers all of the given points
+    pub fn covering(points: impl IntoIterator<Item = Vector2Int>, value: T) -> Self {
+        match get_bounds(points) {
+            Some((min, max)) => Grid::new(min, max, value),
+            None => Grid::default(),
+        }
+    }
+}
+
+impl<T> Grid<T> {
+    /// inclusive min and max corners
+    pub fn bounds(&self) -> (Vector2Int, Vector2Int) {
+        (self.min, self.min + self.size - Vector2Int::new(1, 1))
+    }
+
+    pub fn contains(&self, v: Vector2Int) -> bool {
+        self.index_of(v).is_some()
+    }
+
+    pub fn get(&self, v: Vector2Int) -> Option<&T> {
+        self.index_of(v).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, v: Vector2Int) -> Option<&mut T> {
+        self.index_of(v).map(|i| &mut self.cells[i])
+    }
+
+    /// sets the value at `v`, positions outside of the grid are ignored
+    pub fn set(&mut self, v: Vector2Int, value: T) {
+        if let Some(cell) = self.get_mut(v) {
+            *cell = value;
+        }
+    }
+
+    /// orthogonal neighbours of `v` that lie inside of the grid
+    pub fn neighbours(&self, v: Vector2Int) -> impl Iterator<Item = Vector2Int> + '_ {
+        ORTHO_DIRECTIONS
+            .iter()
+            .map(move |d| v + *d)
+            .filter(|n| self.contains(*n))
+    }
+
+    /// iterates all cells row by row, starting at the min corner
+    pub fn iter(&self) -> impl Iterator<Item = (Vector2Int, &T)> {
+        self.cells.iter().enumerate().map(|(i, value)| {
+            let i = i as i32;
+            (
+                self.min + Vector2Int::new(i % self.size.x, i / self.size.x),
+                value,
+            )
+        })
+    }
+
+    fn index_of(&self, v: Vector2Int) -> Option<usize> {
+        let local = v - self.min;
+        if local.x < 0 || local.y < 0 || local.x >= self.size.x || local.y >= self.size.y {
+            return None;
+        }
+        Some((local.y * self.size.x + local.x) as usize)
+    }
+}
+
+impl<T> Index<Vector2Int> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, v: Vector2Int) -> &T {
+        self.get(v)
+            .unwrap_or_else(|| panic!("{:?} is outside of the grid", v))
+    }
+}
+
+impl<T> IndexMut<Vector2Int> for Grid<T> {
+    fn index_mut(&mut self, v: Vector2Int) -> &mut T {
+        self.get_mut(v)
+            .unwrap_or_else(|| panic!("{:?} is outside of the grid", v))
+    }
+}
+
+/// returns the inclusive min and max corners of a set of points
+fn get_bounds(points: impl IntoIterator<Item = Vector2Int>) -> Option<(Vector2Int, Vector2Int)> {
+    points.into_iter().fold(None, |bounds, v| match bounds {
+        None => Some((v, v)),
+        Some((min, max)) => Some((
+            Vector2Int::new(min.x.min(v.x), min.y.min(v.y)),
+            Vector2Int::new(max.x.max(v.x), max.y.max(v.y)),
+        )),
+    })
+}
@@ -1,12 +1,17 @@
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
     ops::{Add, AddAssign, Div, Mul, Sub, SubAssign},
 };
 
+mod grid;
 mod utils;
+pub use grid::Grid;
 pub use utils::find_path;
 
-#[derive(Copy, Clone, Debug, Default, Ord, PartialOrd, PartialEq, Eq, Hash)]
+#[derive(
+    Copy, Clone, Debug, Default, Ord, PartialOrd, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
 pub struct Vector2Int {
     pub x: i32,
     pub y: i32,
@@ -125,11 +130,7 @@ pub const ORTHO_DIRECTIONS: [Vector2Int; 4] = [
 /// casts a line between two points then returns a vector of all the points between the source and the destination or until a blocker is found in the path
 ///
 /// uses bresenham's line algorithm
-pub fn cast_line(
-    start: Vector2Int,
-    end: Vector2Int,
-    blocker_positions: &HashSet<Vector2Int>,
-) -> Vec<Vector2Int> {
+pub fn cast_line(start: Vector2Int, end: Vector2Int, blockers: &Grid<bool>) -> Vec<Vector2Int> {
     let mut path = Vec::new();
     let mut x0 = start.x;
     let mut y0 = start.y;
@@ -147,7 +148,7 @@ pub fn cast_line(
         path.push(Vector2Int::new(x0, y0));
 
         // return if we've reached a blocker
-        if blocker_positions.contains(&Vector2Int::new(x0, y0)) {
+        if blockers.get(Vector2Int::new(x0, y0)) == Some(&true) {
             return path;
         }
 
@@ -170,17 +171,22 @@ pub fn cast_line(
     path
 }
 
-/// line of sight takes a start point and a list of perimeter points and returns a vector of the points in the path of the line of sight
+/// line of sight takes a start point and a list of perimeter points and returns a grid marking the points in the path of the line of sight
+///
+/// the returned grid has the same bounds as the blocker grid
 pub fn line_of_sight(
     start: Vector2Int,
     area: HashSet<Vector2Int>,
-    blocker_positions: &HashSet<Vector2Int>,
-) -> Vec<Vector2Int> {
-    let mut path = Vec::new();
+    blockers: &Grid<bool>,
+) -> Grid<bool> {
+    let (min, max) = blockers.bounds();
+    let mut visible = Grid::new(min, max, false);
 
     for point in area {
-        path.extend(cast_line(start, point, blocker_positions));
+        for v in cast_line(start, point, blockers) {
+            visible.set(v, true);
+        }
     }
 
-    path
+    visible
 }
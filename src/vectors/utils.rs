@@ -1,54 +1,66 @@
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    collections::{BinaryHeap, HashSet, VecDeque},
 };
 
-use super::{Vector2Int, ORTHO_DIRECTIONS};
+use super::{Grid, Vector2Int};
 
-pub fn find_path(
+/// finds the cheapest orthogonal path between two points
+///
+/// `cost` returns the price of stepping onto a cell, `None` cells can't be entered.
+/// the search is guided by the manhattan distance, so every step has to cost at least 1.
+/// the returned path excludes the start and includes the end
+pub fn find_path<T>(
     start: Vector2Int,
     end: Vector2Int,
-    tiles: &HashSet<Vector2Int>,
+    tiles: &Grid<T>,
+    cost: impl Fn(&T) -> Option<u32>,
     blockers: &HashSet<Vector2Int>,
 ) -> Option<VecDeque<Vector2Int>> {
+    let (min, max) = tiles.bounds();
     let mut queue = BinaryHeap::new();
-    queue.push(Node { v: start, cost: 0 });
-    let mut visited = HashMap::new();
-    visited.insert(start, 0);
-    let mut came_from = HashMap::new();
+    queue.push(Node {
+        v: start,
+        cost: 0,
+        priority: 0,
+    });
+    let mut visited: Grid<Option<u32>> = Grid::new(min, max, None);
+    visited.set(start, Some(0));
+    let mut came_from: Grid<Option<Vector2Int>> = Grid::new(min, max, None);
 
-    while let Some(Node { v, cost }) = queue.pop() {
+    while let Some(node) = queue.pop() {
+        let (v, current) = (node.v, node.cost);
         if v == end {
             break;
         }
-        for dir in ORTHO_DIRECTIONS {
-            let n = v + dir;
-            let new_cost = cost + 1;
-            if !tiles.contains(&n) {
+        for n in tiles.neighbours(v) {
+            let Some(step_cost) = cost(&tiles[n]) else {
                 continue;
-            }
+            };
+            let new_cost = current + step_cost;
             // we allow the target to be a blocker
             if blockers.contains(&n) && n != end {
                 continue;
             }
-            match visited.get(&n) {
-                Some(c) if *c <= new_cost => (),
+            match visited[n] {
+                Some(c) if c <= new_cost => (),
                 _ => {
-                    visited.insert(n, new_cost);
+                    visited[n] = Some(new_cost);
                     queue.push(Node {
                         v: n,
                         cost: new_cost,
+                        priority: new_cost + n.manhattan(end) as u32,
                     });
-                    came_from.insert(n, v);
+                    came_from[n] = Some(v);
                 }
             }
         }
     }
     let mut path = VecDeque::new();
     let mut cur = end;
-    while let Some(v) = came_from.get(&cur) {
+    while let Some(v) = came_from.get(cur).copied().flatten() {
         path.push_front(cur);
-        cur = *v;
+        cur = v;
         if cur == start {
             return Some(path);
         }
@@ -61,13 +73,15 @@ pub fn find_path(
 struct Node {
     pub v: Vector2Int,
     pub cost: u32,
+    // cost so far plus the estimate to the end
+    pub priority: u32,
 }
 
 impl Ord for Node {
     fn cmp(&self, other: &Self) -> Ordering {
         other
-            .cost
-            .cmp(&self.cost)
+            .priority
+            .cmp(&self.priority)
             .then_with(|| self.v.cmp(&other.v))
     }
 }